@@ -0,0 +1,102 @@
+use crate::activitypub::{signature, ACTIVITY_JSON};
+use crate::fetcher::Fetcher;
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Delays before each retry of a failed delivery
+const RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(30),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(30 * 60),
+];
+
+/// POST a signed activity to one inbox
+pub async fn deliver(fetcher: &dyn Fetcher, inbox: &str, activity: &serde_json::Value) -> Result<()> {
+    let url = reqwest::Url::parse(inbox).context("Invalid inbox URL")?;
+    let body = serde_json::to_vec(activity).context("Failed to serialize activity")?;
+
+    let mut headers = vec![("Content-Type".to_string(), ACTIVITY_JSON.to_string())];
+    // reqwest derives Host from the URL itself
+    headers.extend(
+        signature::sign_post(&url, &body, &crate::activitypub::key_id())?
+            .into_iter()
+            .filter(|(name, _)| name != "Host"),
+    );
+
+    let status = fetcher.post(inbox, &headers, body).await.context("Delivery failed")?;
+    if !(200..300).contains(&status) {
+        bail!("Inbox {} answered {}", inbox, status);
+    }
+    Ok(())
+}
+
+/// Deliver an activity to one inbox in the background, retrying with backoff
+pub fn spawn_delivery(inbox: String, activity: serde_json::Value) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        tracing::warn!("No runtime to deliver activity to {}", inbox);
+        return;
+    };
+
+    runtime.spawn(async move {
+        let mut delays = RETRY_DELAYS.iter();
+        loop {
+            match deliver(crate::fetcher::fetcher(), &inbox, &activity).await {
+                Ok(()) => return,
+                Err(e) => match delays.next() {
+                    Some(delay) => {
+                        tracing::warn!("Delivery to {} failed, retrying: {}", inbox, e);
+                        tokio::time::sleep(*delay).await;
+                    }
+                    None => {
+                        tracing::error!("Giving up delivery to {}: {}", inbox, e);
+                        return;
+                    }
+                },
+            }
+        }
+    });
+}
+
+/// Deliver an activity once to every follower's server
+pub fn deliver_to_followers(activity: &serde_json::Value) -> Result<()> {
+    let inboxes: BTreeSet<String> = crate::activitypub::store::followers()?
+        .iter()
+        .map(|follower| follower.delivery_inbox().to_string())
+        .collect();
+    for inbox in inboxes {
+        spawn_delivery(inbox, activity.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ScratchDir, StubFetcher};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn delivers_signed_activities_through_the_fetcher() {
+        let _dir = ScratchDir::new().await;
+        let remote = StubFetcher::default();
+        let activity = json!({ "type": "Accept" });
+
+        deliver(&remote, "https://remote.example/inbox", &activity).await.unwrap();
+
+        let posts = remote.posts();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].url, "https://remote.example/inbox");
+        assert_eq!(posts[0].body, serde_json::to_vec(&activity).unwrap());
+        let header = |name: &str| {
+            posts[0]
+                .headers
+                .iter()
+                .find(|(header, _)| header == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(header("Content-Type"), Some(ACTIVITY_JSON));
+        assert_eq!(header("Host"), None);
+        assert!(header("Signature").is_some_and(|value| value.contains(&crate::activitypub::key_id())));
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use std::path::Path;
+use std::sync::OnceLock;
+
+const KEY_BITS: usize = 2048;
+
+fn key_file() -> String {
+    std::env::var("BLOG_AP_KEY_FILE").unwrap_or_else(|_| "activitypub_key.pem".to_string())
+}
+
+/// Load the actor's key pair, generating and saving one on first use
+fn load_or_generate() -> Result<RsaPrivateKey> {
+    let path = key_file();
+    let path = Path::new(&path);
+
+    if path.exists() {
+        let pem = std::fs::read_to_string(path).context("Failed to read ActivityPub key")?;
+        return RsaPrivateKey::from_pkcs8_pem(&pem).context("Failed to parse ActivityPub key");
+    }
+
+    let key = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS)
+        .context("Failed to generate ActivityPub key")?;
+    let pem = key
+        .to_pkcs8_pem(LineEnding::LF)
+        .context("Failed to encode ActivityPub key")?;
+    std::fs::write(path, pem.as_bytes()).context("Failed to save ActivityPub key")?;
+    Ok(key)
+}
+
+/// Private key used to sign outgoing deliveries
+pub fn private_key() -> Result<&'static RsaPrivateKey> {
+    static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+    if let Some(key) = KEY.get() {
+        return Ok(key);
+    }
+    let key = load_or_generate()?;
+    Ok(KEY.get_or_init(|| key))
+}
+
+/// Public key published on the actor, in PEM form
+pub fn public_key_pem() -> Result<String> {
+    RsaPublicKey::from(private_key()?)
+        .to_public_key_pem(LineEnding::LF)
+        .context("Failed to encode ActivityPub public key")
+}
+
+/// Parse a remote actor's key, which may be SPKI or PKCS#1 encoded
+pub fn parse_public_key(pem: &str) -> Result<RsaPublicKey> {
+    RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .context("Failed to parse remote public key")
+}
@@ -0,0 +1,312 @@
+pub mod delivery;
+pub mod keys;
+pub mod signature;
+pub mod store;
+
+use crate::fetcher::Fetcher;
+use crate::markdown::Post;
+use anyhow::{bail, Context, Result};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::Response,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+const ACTIVITY_STREAMS: &str = "https://www.w3.org/ns/activitystreams";
+const SECURITY: &str = "https://w3id.org/security/v1";
+
+/// Username of the blog's actor, as in `@blog@example.com`
+pub fn username() -> String {
+    std::env::var("BLOG_AP_USERNAME").unwrap_or_else(|_| "blog".to_string())
+}
+
+/// Host part of the public URL, used in `acct:` handles
+fn host() -> String {
+    let base_url = crate::utils::public_base_url();
+    let without_scheme = base_url.split("://").nth(1).unwrap_or(&base_url);
+    without_scheme.split('/').next().unwrap_or_default().to_string()
+}
+
+pub fn actor_url() -> String {
+    format!("{}/ap/actor", crate::utils::public_base_url())
+}
+
+pub fn key_id() -> String {
+    format!("{}#main-key", actor_url())
+}
+
+fn followers_url() -> String {
+    format!("{}/ap/followers", crate::utils::public_base_url())
+}
+
+fn article_url(slug: &str) -> String {
+    format!("{}/ap/posts/{}", crate::utils::public_base_url(), slug)
+}
+
+fn activity_id() -> String {
+    format!(
+        "{}/ap/activities/{}",
+        crate::utils::public_base_url(),
+        uuid::Uuid::new_v4()
+    )
+}
+
+/// Dereference a remote ActivityPub object
+pub async fn fetch_object(fetcher: &dyn Fetcher, url: &str) -> Result<Value> {
+    let page = fetcher.get(url, ACTIVITY_JSON).await?;
+    if !(200..300).contains(&page.status) {
+        bail!("Fetching {} returned {}", url, page.status);
+    }
+    serde_json::from_str(&page.body).context(format!("Invalid JSON at {}", url))
+}
+
+/// A post as an ActivityStreams `Article`
+pub fn article(post: &Post) -> Value {
+    let tags: Vec<Value> = post
+        .tags
+        .iter()
+        .map(|tag| {
+            json!({
+                "type": "Hashtag",
+                "href": format!("{}/tags/{}", crate::utils::public_base_url(), urlencoding::encode(tag)),
+                "name": format!("#{}", tag),
+            })
+        })
+        .collect();
+
+    json!({
+        "id": article_url(&post.slug),
+        "type": "Article",
+        "name": post.title,
+        "summary": crate::markdown::reader::excerpt(&post.content, 280),
+        "content": crate::feeds::post_html(post),
+        "url": crate::feeds::post_url(post),
+        "attributedTo": actor_url(),
+        "published": post.created_at.to_rfc3339(),
+        "updated": post.updated_at.to_rfc3339(),
+        "to": [PUBLIC],
+        "cc": [followers_url()],
+        "tag": tags,
+        "contentMap": { post.lang.clone(): crate::feeds::post_html(post) },
+    })
+}
+
+fn activity(kind: &str, object: Value) -> Value {
+    json!({
+        "@context": ACTIVITY_STREAMS,
+        "id": activity_id(),
+        "type": kind,
+        "actor": actor_url(),
+        "published": chrono::Utc::now().to_rfc3339(),
+        "to": [PUBLIC],
+        "cc": [followers_url()],
+        "object": object,
+    })
+}
+
+/// Record an activity in the outbox and send it to followers
+fn publish(activity: Value) {
+    let result = store::append_to_outbox(&activity)
+        .and_then(|_| delivery::deliver_to_followers(&activity));
+    if let Err(e) = result {
+        tracing::error!("Failed to publish activity: {}", e);
+    }
+}
+
+/// Called by `markdown::writer` when a post is created
+pub fn post_created(post: &Post) {
+    publish(activity("Create", article(post)));
+}
+
+/// Called by `markdown::writer` when a post is edited
+pub fn post_updated(post: &Post) {
+    publish(activity("Update", article(post)));
+}
+
+/// Called by `markdown::writer` when a post is deleted
+pub fn post_deleted(slug: &str) {
+    publish(activity(
+        "Delete",
+        json!({ "id": article_url(slug), "type": "Tombstone" }),
+    ));
+}
+
+fn activity_response(value: Value) -> Response {
+    Response::builder()
+        .header(header::CONTENT_TYPE, ACTIVITY_JSON)
+        .body(Body::from(value.to_string()))
+        .unwrap()
+}
+
+#[derive(Deserialize)]
+pub struct WebFingerQuery {
+    resource: String,
+}
+
+/// `/.well-known/webfinger`: resolve `acct:user@host` to the actor
+pub async fn webfinger(Query(query): Query<WebFingerQuery>) -> Result<Response, StatusCode> {
+    let subject = format!("acct:{}@{}", username(), host());
+    if query.resource != subject && query.resource != actor_url() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let body = json!({
+        "subject": subject,
+        "aliases": [actor_url()],
+        "links": [
+            { "rel": "self", "type": ACTIVITY_JSON, "href": actor_url() },
+            { "rel": "http://webfinger.net/rel/profile-page", "type": "text/html", "href": format!("{}/", crate::utils::public_base_url()) },
+        ],
+    });
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/jrd+json")
+        .body(Body::from(body.to_string()))
+        .unwrap())
+}
+
+/// `/ap/actor`: the blog as a followable actor
+pub async fn actor() -> Result<Response, StatusCode> {
+    let public_key_pem = keys::public_key_pem().map_err(|e| {
+        tracing::error!("ActivityPub key unavailable: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let base_url = crate::utils::public_base_url();
+
+    Ok(activity_response(json!({
+        "@context": [ACTIVITY_STREAMS, SECURITY],
+        "id": actor_url(),
+        "type": "Person",
+        "preferredUsername": username(),
+        "name": crate::utils::site_title(),
+        "url": format!("{}/", base_url),
+        "inbox": format!("{}/ap/inbox", base_url),
+        "outbox": format!("{}/ap/outbox", base_url),
+        "followers": followers_url(),
+        "manuallyApprovesFollowers": false,
+        "publicKey": {
+            "id": key_id(),
+            "owner": actor_url(),
+            "publicKeyPem": public_key_pem,
+        },
+    })))
+}
+
+/// `/ap/outbox`: published activities, most recent first
+pub async fn outbox() -> Result<Response, StatusCode> {
+    let mut activities = store::outbox().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    activities.reverse();
+
+    Ok(activity_response(json!({
+        "@context": ACTIVITY_STREAMS,
+        "id": format!("{}/ap/outbox", crate::utils::public_base_url()),
+        "type": "OrderedCollection",
+        "totalItems": activities.len(),
+        "orderedItems": activities,
+    })))
+}
+
+/// `/ap/followers`: actors following the blog
+pub async fn followers() -> Result<Response, StatusCode> {
+    let followers = store::followers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ids: Vec<&str> = followers.iter().map(|follower| follower.id.as_str()).collect();
+
+    Ok(activity_response(json!({
+        "@context": ACTIVITY_STREAMS,
+        "id": followers_url(),
+        "type": "OrderedCollection",
+        "totalItems": ids.len(),
+        "orderedItems": ids,
+    })))
+}
+
+/// `/ap/posts/{slug}`: a post as an `Article`
+pub async fn post_object(Path(slug): Path<String>) -> Result<Response, StatusCode> {
+    let post = crate::markdown::reader::read_post(&slug).map_err(|_| StatusCode::NOT_FOUND)?;
+    let mut object = article(&post);
+    object["@context"] = json!(ACTIVITY_STREAMS);
+    Ok(activity_response(object))
+}
+
+/// Id of an object that may be embedded or referenced by id
+fn object_id(value: &Value) -> Option<&str> {
+    value
+        .as_str()
+        .or_else(|| value.get("id").and_then(|id| id.as_str()))
+}
+
+/// Accept a follow request and remember where to deliver to the follower
+async fn handle_follow(fetcher: &dyn Fetcher, actor: &str, follow: &Value) -> Result<()> {
+    if follow.get("object").and_then(object_id) != Some(actor_url().as_str()) {
+        bail!("Follow is not addressed to this blog");
+    }
+
+    let remote = fetch_object(fetcher, actor).await?;
+    let inbox = remote
+        .get("inbox")
+        .and_then(|inbox| inbox.as_str())
+        .context("Follower has no inbox")?
+        .to_string();
+    let shared_inbox = remote
+        .pointer("/endpoints/sharedInbox")
+        .and_then(|inbox| inbox.as_str())
+        .map(String::from);
+
+    store::add_follower(store::Follower {
+        id: actor.to_string(),
+        inbox: inbox.clone(),
+        shared_inbox,
+    })?;
+
+    let accept = json!({
+        "@context": ACTIVITY_STREAMS,
+        "id": activity_id(),
+        "type": "Accept",
+        "actor": actor_url(),
+        "object": follow,
+    });
+    delivery::spawn_delivery(inbox, accept);
+    Ok(())
+}
+
+/// `/ap/inbox`: signed activities from other servers
+pub async fn inbox(uri: Uri, headers: HeaderMap, body: Bytes) -> StatusCode {
+    let path = uri.path_and_query().map_or("/ap/inbox", |p| p.as_str());
+    let fetcher = crate::fetcher::fetcher();
+    let signer = match signature::verify_request(fetcher, "post", path, &headers, &body).await {
+        Ok(signer) => signer,
+        Err(e) => {
+            tracing::warn!("Rejected inbox delivery: {}", e);
+            return StatusCode::UNAUTHORIZED;
+        }
+    };
+
+    let Ok(activity) = serde_json::from_slice::<Value>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    // Only the signing actor may act on its own behalf
+    if activity.get("actor").and_then(object_id) != Some(signer.as_str()) {
+        return StatusCode::FORBIDDEN;
+    }
+
+    let result = match activity.get("type").and_then(|kind| kind.as_str()) {
+        Some("Follow") => handle_follow(fetcher, &signer, &activity).await,
+        Some("Undo") => match activity.pointer("/object/type").and_then(|kind| kind.as_str()) {
+            Some("Follow") => store::remove_follower(&signer),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    };
+
+    match result {
+        Ok(()) => StatusCode::ACCEPTED,
+        Err(e) => {
+            tracing::warn!("Failed to process {} activity: {}", signer, e);
+            StatusCode::BAD_REQUEST
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use crate::activitypub::keys;
+use crate::fetcher::Fetcher;
+use anyhow::{anyhow, bail, Context, Result};
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Signed requests older or newer than this are rejected, which bounds how long one can be replayed
+const MAX_CLOCK_SKEW_SECS: i64 = 5 * 60;
+/// Headers every incoming signature must cover
+const REQUIRED_HEADERS: [&str; 4] = ["(request-target)", "host", "date", "digest"];
+
+/// `Digest` header value for a request body
+pub fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", STANDARD.encode(Sha256::digest(body)))
+}
+
+pub fn http_date(date: &DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Headers to send with a signed POST of `body` to `url`
+pub fn sign_post(url: &reqwest::Url, body: &[u8], key_id: &str) -> Result<Vec<(String, String)>> {
+    Ok(sign_post_with(url, body, key_id, keys::private_key()?))
+}
+
+fn sign_post_with(
+    url: &reqwest::Url,
+    body: &[u8],
+    key_id: &str,
+    private_key: &RsaPrivateKey,
+) -> Vec<(String, String)> {
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let target = match url.query() {
+        Some(query) => format!("post {}?{}", url.path(), query),
+        None => format!("post {}", url.path()),
+    };
+    let date = http_date(&Utc::now());
+    let digest = digest(body);
+
+    let signing_string = format!(
+        "(request-target): {}\nhost: {}\ndate: {}\ndigest: {}",
+        target, host, date, digest
+    );
+    let signing_key = SigningKey::<Sha256>::new(private_key.clone());
+    let signature = STANDARD.encode(signing_key.sign(signing_string.as_bytes()).to_bytes());
+
+    vec![
+        ("Host".to_string(), host),
+        ("Date".to_string(), date),
+        ("Digest".to_string(), digest),
+        (
+            "Signature".to_string(),
+            format!(
+                r#"keyId="{}",algorithm="rsa-sha256",headers="(request-target) host date digest",signature="{}""#,
+                key_id, signature
+            ),
+        ),
+    ]
+}
+
+/// Parameters of a `Signature` header
+struct SignatureParams {
+    key_id: String,
+    headers: Vec<String>,
+    signature: Vec<u8>,
+}
+
+fn parse_signature_header(value: &str) -> Result<SignatureParams> {
+    let params: HashMap<&str, &str> = value
+        .split(',')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim().trim_matches('"')))
+        .collect();
+
+    let key_id = params.get("keyId").ok_or_else(|| anyhow!("Signature has no keyId"))?;
+    let headers = params
+        .get("headers")
+        .map_or_else(|| vec!["date".to_string()], |h| h.split_whitespace().map(str::to_lowercase).collect());
+    let signature = STANDARD
+        .decode(params.get("signature").ok_or_else(|| anyhow!("Signature has no value"))?)
+        .context("Signature is not valid base64")?;
+
+    Ok(SignatureParams {
+        key_id: key_id.to_string(),
+        headers,
+        signature,
+    })
+}
+
+/// Remote public keys by key id, with the actor that owns them
+fn key_cache() -> &'static DashMap<String, (String, RsaPublicKey)> {
+    static CACHE: OnceLock<DashMap<String, (String, RsaPublicKey)>> = OnceLock::new();
+    CACHE.get_or_init(DashMap::new)
+}
+
+/// The key with the given id among those a document publishes
+fn listed_key<'a>(document: &'a Value, key_id: &str) -> Option<&'a Value> {
+    // The key is usually embedded in the actor, but may also be served on its own
+    let keys = match document.get("publicKey") {
+        Some(Value::Array(keys)) => keys.iter().collect(),
+        Some(key) => vec![key],
+        None => vec![document],
+    };
+    keys.into_iter()
+        .find(|key| key.get("id").and_then(|id| id.as_str()) == Some(key_id))
+}
+
+fn str_field<'a>(value: &'a Value, name: &str) -> Option<&'a str> {
+    value.get(name).and_then(|value| value.as_str())
+}
+
+/// Fetch the key behind a key id and return the actor that owns it with the key
+///
+/// Whoever signed the request picks the key id, so the owner its document names only counts
+/// when that actor publishes the same key id itself.
+async fn fetch_key(fetcher: &dyn Fetcher, key_id: &str) -> Result<(String, RsaPublicKey)> {
+    let document_url = key_id.split('#').next().unwrap_or(key_id);
+    let document = crate::activitypub::fetch_object(fetcher, document_url).await?;
+    let owner = listed_key(&document, key_id)
+        .ok_or_else(|| anyhow!("Key {} not found", key_id))
+        .and_then(|key| {
+            str_field(key, "owner").ok_or_else(|| anyhow!("Key {} has no owner", key_id))
+        })?
+        .to_string();
+
+    let actor = if owner == document_url {
+        document
+    } else {
+        crate::activitypub::fetch_object(fetcher, &owner).await?
+    };
+    if str_field(&actor, "id") != Some(owner.as_str()) {
+        bail!("Actor document at {} has a different id", owner);
+    }
+    let key = listed_key(&actor, key_id)
+        .filter(|key| str_field(key, "owner") == Some(owner.as_str()))
+        .ok_or_else(|| anyhow!("{} does not publish key {}", owner, key_id))?;
+    let pem = str_field(key, "publicKeyPem").ok_or_else(|| anyhow!("Key {} has no PEM", key_id))?;
+
+    Ok((owner, keys::parse_public_key(pem)?))
+}
+
+/// Verify the HTTP signature of an incoming request and return the actor that signed it
+pub async fn verify_request(
+    fetcher: &dyn Fetcher,
+    method: &str,
+    path_and_query: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<String> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let params = parse_signature_header(header("signature").ok_or_else(|| anyhow!("Request is not signed"))?)?;
+    for required in REQUIRED_HEADERS {
+        if !params.headers.iter().any(|h| h == required) {
+            bail!("Signature does not cover {}", required);
+        }
+    }
+
+    if header("digest") != Some(digest(body).as_str()) {
+        bail!("Digest does not match the body");
+    }
+    let date = DateTime::parse_from_rfc2822(header("date").unwrap_or_default())
+        .context("Invalid Date header")?;
+    if (Utc::now() - date.with_timezone(&Utc)).num_seconds().abs() > MAX_CLOCK_SKEW_SECS {
+        bail!("Date header is too far from the current time");
+    }
+
+    let signing_string = params
+        .headers
+        .iter()
+        .map(|name| match name.as_str() {
+            "(request-target)" => Ok(format!(
+                "(request-target): {} {}",
+                method.to_lowercase(),
+                path_and_query
+            )),
+            name => header(name)
+                .map(|value| format!("{}: {}", name, value))
+                .ok_or_else(|| anyhow!("Signed header {} is missing", name)),
+        })
+        .collect::<Result<Vec<_>>>()?
+        .join("\n");
+    let signature = Signature::try_from(params.signature.as_slice()).context("Malformed signature")?;
+
+    let verify = |key: &RsaPublicKey| {
+        VerifyingKey::<Sha256>::new(key.clone())
+            .verify(signing_string.as_bytes(), &signature)
+            .is_ok()
+    };
+
+    // A cached key may have been rotated, so retry once with a fresh copy
+    if let Some(cached) = key_cache().get(&params.key_id).map(|entry| entry.clone()) {
+        if verify(&cached.1) {
+            return Ok(cached.0);
+        }
+    }
+    let (owner, key) = fetch_key(fetcher, &params.key_id).await?;
+    if !verify(&key) {
+        bail!("Signature verification failed");
+    }
+    key_cache().insert(params.key_id, (owner.clone(), key));
+    Ok(owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::StubFetcher;
+    use rsa::pkcs8::{EncodePublicKey, LineEnding};
+    use serde_json::json;
+
+    const KEY_BITS: usize = 1024;
+
+    fn public_pem(key: &RsaPrivateKey) -> String {
+        RsaPublicKey::from(key).to_public_key_pem(LineEnding::LF).unwrap()
+    }
+
+    fn actor(base: &str, path: &str, key: &RsaPrivateKey) -> Value {
+        let id = format!("{}{}", base, path);
+        json!({
+            "id": id,
+            "type": "Person",
+            "inbox": format!("{}/inbox", id),
+            "publicKey": {
+                "id": format!("{}#main-key", id),
+                "owner": id,
+                "publicKeyPem": public_pem(key),
+            },
+        })
+    }
+
+    /// Deliver `body` to our inbox signed with `key`, as a remote server would
+    async fn deliver(
+        remote: &StubFetcher,
+        key: &RsaPrivateKey,
+        key_id: &str,
+        body: &[u8],
+    ) -> Result<String> {
+        let inbox = reqwest::Url::parse("https://blog.example/ap/inbox").unwrap();
+        let mut headers = HeaderMap::new();
+        for (name, value) in sign_post_with(&inbox, body, key_id, key) {
+            headers.insert(
+                axum::http::HeaderName::from_bytes(name.to_lowercase().as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        verify_request(remote, "POST", "/ap/inbox", &headers, body).await
+    }
+
+    #[tokio::test]
+    async fn accepts_a_key_published_by_its_owner() {
+        let alice = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).unwrap();
+        let base = "https://accepts.example";
+        let remote = StubFetcher::default().page(
+            &format!("{}/users/alice", base),
+            actor(base, "/users/alice", &alice).to_string(),
+        );
+
+        let key_id = format!("{}/users/alice#main-key", base);
+        let signer = deliver(&remote, &alice, &key_id, br#"{"type":"Follow"}"#).await.unwrap();
+        assert_eq!(signer, format!("{}/users/alice", base));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_key_claiming_someone_else_as_owner() {
+        let alice = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).unwrap();
+        let attacker = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).unwrap();
+        let base = "https://forged-owner.example";
+        let evil_key = json!({
+            "id": format!("{}/evil/key", base),
+            "publicKey": {
+                "id": format!("{}/evil/key#k", base),
+                "owner": format!("{}/users/alice", base),
+                "publicKeyPem": public_pem(&attacker),
+            },
+        });
+        let remote = StubFetcher::default()
+            .page(
+                &format!("{}/users/alice", base),
+                actor(base, "/users/alice", &alice).to_string(),
+            )
+            .page(&format!("{}/evil/key", base), evil_key.to_string());
+
+        let key_id = format!("{}/evil/key#k", base);
+        let result = deliver(&remote, &attacker, &key_id, br#"{"type":"Follow"}"#).await;
+        assert!(result.is_err(), "forged owner accepted: {:?}", result);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_signature_by_another_key() {
+        let alice = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).unwrap();
+        let attacker = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).unwrap();
+        let base = "https://other-key.example";
+        let remote = StubFetcher::default().page(
+            &format!("{}/users/alice", base),
+            actor(base, "/users/alice", &alice).to_string(),
+        );
+
+        let key_id = format!("{}/users/alice#main-key", base);
+        assert!(deliver(&remote, &attacker, &key_id, br#"{"type":"Undo"}"#).await.is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const FOLLOWERS_FILE: &str = "followers.json";
+const OUTBOX_FILE: &str = "outbox.json";
+
+/// A remote actor following the blog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Follower {
+    pub id: String,
+    pub inbox: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_inbox: Option<String>,
+}
+
+impl Follower {
+    /// Inbox to deliver to, sharing one delivery between followers on the same server
+    pub fn delivery_inbox(&self) -> &str {
+        self.shared_inbox.as_deref().unwrap_or(&self.inbox)
+    }
+}
+
+/// Serializes read-modify-write cycles on the store files
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn read<T: DeserializeOwned>(file: &str) -> Result<Vec<T>> {
+    let path = Path::new(file);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(path).context(format!("Failed to read {}", file))?;
+    serde_json::from_str(&content).context(format!("Failed to parse {}", file))
+}
+
+fn write<T: Serialize>(file: &str, items: &[T]) -> Result<()> {
+    let content = serde_json::to_string_pretty(items).context(format!("Failed to serialize {}", file))?;
+    std::fs::write(file, content).context(format!("Failed to write {}", file))
+}
+
+pub fn followers() -> Result<Vec<Follower>> {
+    let _guard = lock().lock().unwrap();
+    read(FOLLOWERS_FILE)
+}
+
+/// Add or refresh a follower
+pub fn add_follower(follower: Follower) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut followers: Vec<Follower> = read(FOLLOWERS_FILE)?;
+    followers.retain(|existing| existing.id != follower.id);
+    followers.push(follower);
+    write(FOLLOWERS_FILE, &followers)
+}
+
+pub fn remove_follower(actor: &str) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut followers: Vec<Follower> = read(FOLLOWERS_FILE)?;
+    followers.retain(|existing| existing.id != actor);
+    write(FOLLOWERS_FILE, &followers)
+}
+
+/// Activities published by the blog, oldest first
+pub fn outbox() -> Result<Vec<serde_json::Value>> {
+    let _guard = lock().lock().unwrap();
+    read(OUTBOX_FILE)
+}
+
+pub fn append_to_outbox(activity: &serde_json::Value) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut activities: Vec<serde_json::Value> = read(OUTBOX_FILE)?;
+    activities.push(activity.clone());
+    write(OUTBOX_FILE, &activities)
+}
@@ -0,0 +1,408 @@
+pub mod store;
+
+use crate::i18n::Locale;
+use crate::utils::escape_html;
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::{Html, Json},
+};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+const DEFAULT_DAYS: i64 = 30;
+/// Views are counted in memory and written out this often, so a restart loses at most this much
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+const TOP_ENTRIES: usize = 10;
+/// User agents containing any of these are not counted
+const BOT_MARKERS: [&str; 16] = [
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "fetch",
+    "preview",
+    "facebookexternalhit",
+    "embedly",
+    "headless",
+    "lighthouse",
+    "curl",
+    "wget",
+    "python",
+    "go-http-client",
+    "java/",
+    "monitor",
+];
+
+/// Days of statistics kept, older ones are dropped as new views come in
+fn retention_days() -> i64 {
+    std::env::var("BLOG_ANALYTICS_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(365)
+}
+
+/// Visitors seen today, known only by a hash salted with a key that is never written anywhere
+///
+/// The salt changes with the date, so the same reader cannot be followed from one day to the next.
+struct Today {
+    date: NaiveDate,
+    salt: [u8; 32],
+    visitors: HashSet<[u8; 32]>,
+    post_visitors: HashSet<(String, [u8; 32])>,
+}
+
+fn today() -> &'static Mutex<Option<Today>> {
+    static TODAY: OnceLock<Mutex<Option<Today>>> = OnceLock::new();
+    TODAY.get_or_init(|| Mutex::new(None))
+}
+
+fn is_bot(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_ascii_lowercase();
+    BOT_MARKERS.iter().any(|marker| user_agent.contains(marker))
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Domain of the referring page; links within the blog are not referrals
+fn referrer_domain(headers: &HeaderMap) -> Option<String> {
+    let url = reqwest::Url::parse(header_value(headers, "referer")?).ok()?;
+    let domain = url.host_str()?.trim_start_matches("www.").to_ascii_lowercase();
+    let own_hosts = [
+        reqwest::Url::parse(&crate::utils::public_base_url())
+            .ok()
+            .and_then(|url| url.host_str().map(String::from)),
+        header_value(headers, "host").map(|host| host.split(':').next().unwrap_or(host).to_string()),
+    ];
+    let own = own_hosts
+        .iter()
+        .flatten()
+        .any(|host| host.trim_start_matches("www.").eq_ignore_ascii_case(&domain));
+    (!own).then_some(domain)
+}
+
+/// Count a view of a post; called by `get_post`
+///
+/// Neither addresses nor cookies are stored: the address and user agent only go into today's
+/// salted hash, which lives in memory to tell visitors apart and is forgotten at midnight UTC.
+pub fn record(slug: &str, headers: &HeaderMap) {
+    // The static export and most scripts send no user agent at all
+    let Some(user_agent) = header_value(headers, "user-agent") else {
+        return;
+    };
+    let prefetch = [header_value(headers, "purpose"), header_value(headers, "sec-purpose")]
+        .iter()
+        .flatten()
+        .any(|purpose| purpose.contains("prefetch"));
+    if is_bot(user_agent) || prefetch {
+        return;
+    }
+
+    let date = Utc::now().date_naive();
+    let (new_visitor, new_post_visitor) = {
+        let mut today = today().lock().unwrap();
+        if today.as_ref().is_none_or(|today| today.date != date) {
+            *today = Some(Today {
+                date,
+                salt: rand::random(),
+                visitors: HashSet::new(),
+                post_visitors: HashSet::new(),
+            });
+        }
+        let today = today.as_mut().expect("today is set");
+        let visitor: [u8; 32] = Sha256::new()
+            .chain_update(today.salt)
+            .chain_update(crate::spam::client_ip(headers).unwrap_or_default())
+            .chain_update([0])
+            .chain_update(user_agent)
+            .finalize()
+            .into();
+        (
+            today.visitors.insert(visitor),
+            today.post_visitors.insert((slug.to_string(), visitor)),
+        )
+    };
+
+    let view = store::View {
+        date,
+        slug,
+        new_visitor,
+        new_post_visitor,
+        referrer: referrer_domain(headers),
+    };
+    if let Err(e) = store::record(view, date - Duration::days(retention_days() - 1)) {
+        tracing::error!("Failed to record view of {}: {}", slug, e);
+    }
+}
+
+/// Write counted views to disk in the background
+pub fn spawn_flusher() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            match tokio::task::spawn_blocking(store::flush).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::error!("Failed to save analytics: {}", e),
+                Err(e) => tracing::error!("Analytics flush panicked: {}", e),
+            }
+        }
+    });
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyTotal {
+    pub date: NaiveDate,
+    pub views: u64,
+    pub visitors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostTotal {
+    pub slug: String,
+    pub title: Option<String>,
+    pub views: u64,
+    pub visitors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferrerTotal {
+    pub domain: String,
+    pub views: u64,
+}
+
+/// What the dashboard shows for a period ending today
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub views: u64,
+    /// Sum of the daily unique visitors
+    pub visitors: u64,
+    /// Views in the period of the same length just before, to show the trend
+    pub previous_views: u64,
+    pub daily: Vec<DailyTotal>,
+    pub top_posts: Vec<PostTotal>,
+    pub referrers: Vec<ReferrerTotal>,
+}
+
+impl Summary {
+    pub fn for_days(days: i64) -> anyhow::Result<Self> {
+        let to = Utc::now().date_naive();
+        let from = to - Duration::days(days - 1);
+        let previous_from = from - Duration::days(days);
+        let stats = store::range(previous_from, to)?;
+
+        let previous_views = stats
+            .range(..from)
+            .flat_map(|(_, day)| day.posts.values())
+            .map(|post| post.views)
+            .sum();
+
+        let mut daily = Vec::new();
+        let mut posts: BTreeMap<&str, store::PostDay> = BTreeMap::new();
+        let mut referrers: BTreeMap<&str, u64> = BTreeMap::new();
+        for date in from.iter_days().take_while(|date| *date <= to) {
+            let Some(day) = stats.get(&date) else {
+                daily.push(DailyTotal { date, views: 0, visitors: 0 });
+                continue;
+            };
+            daily.push(DailyTotal {
+                date,
+                views: day.posts.values().map(|post| post.views).sum(),
+                visitors: day.visitors,
+            });
+            for (slug, post) in &day.posts {
+                let total = posts.entry(slug).or_default();
+                total.views += post.views;
+                total.visitors += post.visitors;
+            }
+            for (domain, views) in &day.referrers {
+                *referrers.entry(domain).or_default() += views;
+            }
+        }
+
+        let titles: HashMap<String, String> = crate::markdown::reader::read_posts()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|post| (post.slug, post.title))
+            .collect();
+        let mut top_posts: Vec<PostTotal> = posts
+            .into_iter()
+            .map(|(slug, total)| PostTotal {
+                slug: slug.to_string(),
+                title: titles.get(slug).cloned(),
+                views: total.views,
+                visitors: total.visitors,
+            })
+            .collect();
+        top_posts.sort_by_key(|post| std::cmp::Reverse(post.views));
+        top_posts.truncate(TOP_ENTRIES);
+        let mut referrers: Vec<ReferrerTotal> = referrers
+            .into_iter()
+            .map(|(domain, views)| ReferrerTotal { domain: domain.to_string(), views })
+            .collect();
+        referrers.sort_by_key(|referrer| std::cmp::Reverse(referrer.views));
+        referrers.truncate(TOP_ENTRIES);
+
+        Ok(Self {
+            from,
+            to,
+            views: daily.iter().map(|day| day.views).sum(),
+            visitors: daily.iter().map(|day| day.visitors).sum(),
+            previous_views,
+            daily,
+            top_posts,
+            referrers,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    pub days: Option<i64>,
+}
+
+impl DashboardQuery {
+    fn days(&self) -> i64 {
+        self.days.unwrap_or(DEFAULT_DAYS).clamp(1, retention_days())
+    }
+}
+
+pub async fn serve_dashboard_page(locale: Locale) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/admin/analytics.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Html(locale.localize(&template)))
+}
+
+fn summary_for(query: &DashboardQuery) -> Result<Summary, StatusCode> {
+    Summary::for_days(query.days()).map_err(|e| {
+        tracing::error!("Failed to read analytics: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// `/admin/analytics/summary?days=30`
+pub async fn summary(Query(query): Query<DashboardQuery>) -> Result<Json<Summary>, StatusCode> {
+    summary_for(&query).map(Json)
+}
+
+/// `/admin/analytics/summary/html`: totals, daily chart and top lists for the dashboard page
+pub async fn summary_html(
+    locale: Locale,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Html<String>, StatusCode> {
+    let summary = summary_for(&query)?;
+
+    let trend = if summary.previous_views == 0 {
+        String::new()
+    } else {
+        let change = (summary.views as f64 / summary.previous_views as f64 - 1.0) * 100.0;
+        format!(
+            r#"<span class="analytics-trend {}" title="{}">{:+.0}%</span>"#,
+            if change < 0.0 { "down" } else { "up" },
+            locale.t("analytics.previous_period"),
+            change
+        )
+    };
+    let totals = format!(
+        r#"<div class="analytics-totals">
+    <div class="analytics-total"><span class="analytics-value">{}</span> <span class="analytics-label">{}</span> {}</div>
+    <div class="analytics-total"><span class="analytics-value">{}</span> <span class="analytics-label">{}</span></div>
+</div>"#,
+        summary.views,
+        locale.t("analytics.views"),
+        trend,
+        summary.visitors,
+        locale.t("analytics.visitors")
+    );
+
+    let busiest = summary.daily.iter().map(|day| day.views).max().unwrap_or(0).max(1);
+    let bars: String = summary
+        .daily
+        .iter()
+        .map(|day| {
+            format!(
+                r#"<div class="analytics-bar" style="height: {:.1}%" title="{}: {} {}"></div>"#,
+                day.views as f64 * 100.0 / busiest as f64,
+                day.date,
+                day.views,
+                locale.t("analytics.views")
+            )
+        })
+        .collect();
+    let chart = format!(
+        r#"<h2>{}</h2><div class="analytics-chart">{}</div>"#,
+        locale.t("analytics.daily_views"),
+        bars
+    );
+
+    let empty_row = |columns: usize| {
+        format!(
+            r#"<tr><td colspan="{}" class="analytics-empty">{}</td></tr>"#,
+            columns,
+            locale.t("analytics.no_data")
+        )
+    };
+    let mut post_rows: String = summary
+        .top_posts
+        .iter()
+        .map(|post| {
+            format!(
+                r#"<tr><td><a href="/posts/{}">{}</a></td><td>{}</td><td>{}</td></tr>"#,
+                post.slug,
+                escape_html(post.title.as_deref().unwrap_or(&post.slug)),
+                post.views,
+                post.visitors
+            )
+        })
+        .collect();
+    if post_rows.is_empty() {
+        post_rows = empty_row(3);
+    }
+    let mut referrer_rows: String = summary
+        .referrers
+        .iter()
+        .map(|referrer| {
+            format!(
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape_html(&referrer.domain),
+                referrer.views
+            )
+        })
+        .collect();
+    if referrer_rows.is_empty() {
+        referrer_rows = empty_row(2);
+    }
+    let tables = format!(
+        r#"<div class="analytics-tables">
+    <table class="analytics-table">
+        <caption>{}</caption>
+        <thead><tr><th>{}</th><th>{}</th><th>{}</th></tr></thead>
+        <tbody>{}</tbody>
+    </table>
+    <table class="analytics-table">
+        <caption>{}</caption>
+        <thead><tr><th>{}</th><th>{}</th></tr></thead>
+        <tbody>{}</tbody>
+    </table>
+</div>"#,
+        locale.t("analytics.top_posts"),
+        locale.t("analytics.post"),
+        locale.t("analytics.views"),
+        locale.t("analytics.visitors"),
+        post_rows,
+        locale.t("analytics.referrers"),
+        locale.t("analytics.domain"),
+        locale.t("analytics.views"),
+        referrer_rows
+    );
+
+    Ok(Html(format!("{}\n{}\n{}", totals, chart, tables)))
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const ANALYTICS_FILE: &str = "analytics.json";
+/// Referring domains counted per day; views from further new domains still count, without their referrer
+const MAX_REFERRERS_PER_DAY: usize = 200;
+
+/// Views of one post on one day
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PostDay {
+    pub views: u64,
+    /// Distinct visitors, as far as the salt of that day could tell them apart
+    pub visitors: u64,
+}
+
+/// Everything counted on one day; nothing in here identifies a reader
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DayStats {
+    #[serde(default)]
+    pub visitors: u64,
+    #[serde(default)]
+    pub posts: BTreeMap<String, PostDay>,
+    /// Views by referring domain
+    #[serde(default)]
+    pub referrers: BTreeMap<String, u64>,
+}
+
+pub type Stats = BTreeMap<NaiveDate, DayStats>;
+
+/// A view that passed the bot filter
+pub struct View<'a> {
+    pub date: NaiveDate,
+    pub slug: &'a str,
+    /// First view of any post by this visitor today
+    pub new_visitor: bool,
+    /// First view of this post by this visitor today
+    pub new_post_visitor: bool,
+    pub referrer: Option<String>,
+}
+
+/// Statistics as loaded, with whether views came in since they were last written
+#[derive(Default)]
+struct Cache {
+    stats: Option<Stats>,
+    dirty: bool,
+}
+
+/// Counted on every post view, so kept in memory and written out by `flush`
+fn cache() -> &'static Mutex<Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(Cache::default()))
+}
+
+fn read() -> Result<Stats> {
+    let path = Path::new(ANALYTICS_FILE);
+    if !path.exists() {
+        return Ok(Stats::new());
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read analytics.json")?;
+    serde_json::from_str(&content).context("Failed to parse analytics.json")
+}
+
+fn with<R>(f: impl FnOnce(&mut Stats) -> (R, bool)) -> Result<R> {
+    let mut cache = cache().lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.stats.is_none() {
+        cache.stats = Some(read()?);
+    }
+    let stats = cache.stats.as_mut().expect("analytics are loaded");
+    let (result, changed) = f(stats);
+    cache.dirty |= changed;
+    Ok(result)
+}
+
+/// Write the statistics if views came in since the last flush; blocks on the file system
+pub fn flush() -> Result<()> {
+    let content = {
+        let mut cache = cache().lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(stats) = cache.stats.as_ref().filter(|_| cache.dirty) else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(stats).context("Failed to serialize analytics")?;
+        cache.dirty = false;
+        content
+    };
+    // Written outside the lock so views are not held up by the disk
+    std::fs::write(ANALYTICS_FILE, content).context("Failed to write analytics.json")
+}
+
+/// Count a view, dropping days older than `keep_from`
+pub fn record(view: View, keep_from: NaiveDate) -> Result<()> {
+    with(|stats| {
+        stats.retain(|date, _| *date >= keep_from);
+        let day = stats.entry(view.date).or_default();
+        if view.new_visitor {
+            day.visitors += 1;
+        }
+        let post = day.posts.entry(view.slug.to_string()).or_default();
+        post.views += 1;
+        if view.new_post_visitor {
+            post.visitors += 1;
+        }
+        if let Some(referrer) = view.referrer {
+            if day.referrers.len() < MAX_REFERRERS_PER_DAY || day.referrers.contains_key(&referrer) {
+                *day.referrers.entry(referrer).or_default() += 1;
+            }
+        }
+        ((), true)
+    })
+}
+
+/// Days from `from` to `to`, both included
+pub fn range(from: NaiveDate, to: NaiveDate) -> Result<Stats> {
+    with(|stats| {
+        let days = stats
+            .range(from..=to)
+            .map(|(date, day)| (*date, day.clone()))
+            .collect();
+        (days, false)
+    })
+}
@@ -0,0 +1,195 @@
+use crate::i18n::Locale;
+use crate::markdown::Post;
+use crate::utils::escape_html;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::Html,
+};
+use chrono::Datelike;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A year, or a month within a year
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Period {
+    pub year: i32,
+    pub month: Option<u32>,
+}
+
+impl Period {
+    pub fn month_of(post: &Post) -> Self {
+        Self {
+            year: post.created_at.year(),
+            month: Some(post.created_at.month()),
+        }
+    }
+
+    /// Parse the path segments of `/archive/{year}/{month}`
+    pub fn parse(year: &str, month: Option<&str>) -> Option<Self> {
+        let year = year.parse().ok()?;
+        let month = match month {
+            Some(month) => Some(month.parse().ok().filter(|m| (1..=12).contains(m))?),
+            None => None,
+        };
+        Some(Self { year, month })
+    }
+
+    pub fn contains(&self, post: &Post) -> bool {
+        post.created_at.year() == self.year
+            && self.month.is_none_or(|month| post.created_at.month() == month)
+    }
+
+    pub fn path(&self) -> String {
+        match self.month {
+            Some(month) => format!("/archive/{}/{:02}", self.year, month),
+            None => format!("/archive/{}", self.year),
+        }
+    }
+
+    pub fn label(&self, locale: &Locale) -> String {
+        match self.month {
+            Some(month) => format!("{} {}", locale.t(&format!("month.{}", month)), self.year),
+            None => self.year.to_string(),
+        }
+    }
+}
+
+/// Published posts in the reader's language, most recent first
+fn archived_posts(locale: &Locale) -> Vec<Post> {
+    let posts = crate::feeds::scoped_posts(&crate::feeds::FeedScope::All).unwrap_or_default();
+    crate::select_language(posts, None, locale)
+}
+
+/// Number of posts per month, most recent first
+pub fn counts(posts: &[Post]) -> Vec<(Period, usize)> {
+    let mut counts: BTreeMap<Period, usize> = BTreeMap::new();
+    for post in posts {
+        *counts.entry(Period::month_of(post)).or_default() += 1;
+    }
+    counts.into_iter().rev().collect()
+}
+
+/// Posts grouped under a heading for each month, expects posts sorted most recent first
+pub fn render_groups(posts: &[Post], locale: &Locale) -> String {
+    if posts.is_empty() {
+        return format!("<p class='no-posts'>{}</p>", locale.t("posts.none"));
+    }
+
+    let mut groups: Vec<(Period, Vec<Post>)> = Vec::new();
+    for post in posts {
+        let period = Period::month_of(post);
+        match groups.last_mut() {
+            Some((last, group)) if *last == period => group.push(post.clone()),
+            _ => groups.push((period, vec![post.clone()])),
+        }
+    }
+
+    let mut html = String::new();
+    for (period, group) in groups {
+        html.push_str(&format!(
+            r#"
+<section class="archive-period">
+    <h3 class="archive-heading"><a href="{}">{}</a></h3>
+    <div class="posts-grid">{}</div>
+</section>
+"#,
+            period.path(),
+            escape_html(&period.label(locale)),
+            crate::render_post_cards(&group, locale)
+        ));
+    }
+    html
+}
+
+/// Sidebar list of months with their post counts
+pub fn render_widget(posts: &[Post], locale: &Locale) -> String {
+    let mut html = format!(
+        r#"<h3 class="archive-widget-title"><a href="/archive">{}</a></h3><ul class="archive-counts">"#,
+        locale.t("archive.title")
+    );
+    for (period, count) in counts(posts) {
+        html.push_str(&format!(
+            r#"<li><a href="{}">{}</a> <span class="archive-count">({})</span></li>"#,
+            period.path(),
+            escape_html(&period.label(locale)),
+            count
+        ));
+    }
+    html.push_str("</ul>");
+    html
+}
+
+fn render_page(period: Option<Period>, locale: &Locale) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/archive.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let all_posts = archived_posts(locale);
+    let posts: Vec<Post> = match period {
+        Some(period) => all_posts
+            .iter()
+            .filter(|post| period.contains(post))
+            .cloned()
+            .collect(),
+        None => all_posts.clone(),
+    };
+    if period.is_some() && posts.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let heading = match period {
+        Some(period) => format!("{} {}", locale.t("archive.title"), period.label(locale)),
+        None => locale.t("archive.title"),
+    };
+
+    Ok(Html(
+        locale
+            .localize(&template)
+            .replace("{{ heading }}", &escape_html(&heading))
+            .replace("{{ archive | safe }}", &render_groups(&posts, locale))
+            .replace("{{ archive_widget | safe }}", &render_widget(&all_posts, locale)),
+    ))
+}
+
+pub async fn archive_index(locale: Locale) -> Result<Html<String>, StatusCode> {
+    render_page(None, &locale)
+}
+
+pub async fn archive_year(
+    Path(year): Path<String>,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    let period = Period::parse(&year, None).ok_or(StatusCode::NOT_FOUND)?;
+    render_page(Some(period), &locale)
+}
+
+pub async fn archive_month(
+    Path((year, month)): Path<(String, String)>,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    let period = Period::parse(&year, Some(&month)).ok_or(StatusCode::NOT_FOUND)?;
+    render_page(Some(period), &locale)
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveQuery {
+    year: Option<i32>,
+    month: Option<u32>,
+}
+
+/// HTMX fragment with the grouped posts of a period, or of every period
+pub async fn archive_html(locale: Locale, Query(query): Query<ArchiveQuery>) -> Html<String> {
+    let mut posts = archived_posts(&locale);
+    if let Some(year) = query.year {
+        let period = Period {
+            year,
+            month: query.month,
+        };
+        posts.retain(|post| period.contains(post));
+    }
+    Html(render_groups(&posts, &locale))
+}
+
+/// HTMX fragment with the post counts per month
+pub async fn archive_widget(locale: Locale) -> Html<String> {
+    Html(render_widget(&archived_posts(&locale), &locale))
+}
@@ -6,6 +6,16 @@ use serde::{Deserialize, Serialize};
 pub struct Claims {
     pub sub: String,
     pub roles: Vec<String>,
+    /// Display name from the token, or the username when Keycloak has no name for the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Claims {
+    /// Name to show for the user, falling back to the subject when the token carries none
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.sub.clone())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +29,10 @@ pub struct TokenClaims {
     pub realm_access: Option<RealmAccess>,
     #[serde(rename = "resource_access")]
     pub resource_access: Option<ResourceAccess>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -157,6 +171,7 @@ async fn decode_and_validate_token(token: &str, config: &KeycloakConfig) -> Resu
     Ok(Claims {
         sub: token_data.claims.sub,
         roles: normalize_roles(roles),
+        name: token_data.claims.name.or(token_data.claims.preferred_username),
     })
 }
 
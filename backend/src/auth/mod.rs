@@ -109,6 +109,28 @@ pub async fn auth_middleware(
     Ok(next.run(request).await)
 }
 
+/// Token from the Authorization header or the `token` cookie, for routes open to everyone
+pub fn token_from_headers(headers: &HeaderMap) -> Option<String> {
+    if let Some(auth_header) = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+    {
+        return jwt::extract_token_from_header(auth_header).ok().map(String::from);
+    }
+    headers
+        .get(axum::http::header::COOKIE)
+        .and_then(|h| h.to_str().ok())?
+        .split(';')
+        .map(|c| c.trim())
+        .find_map(|c| c.strip_prefix("token="))
+        .map(String::from)
+}
+
+/// Claims of the visitor, when they are logged in with a valid token
+pub async fn optional_claims(headers: &HeaderMap) -> Option<jwt::Claims> {
+    jwt::validate_token(&token_from_headers(headers)?).await.ok()
+}
+
 /// Extract claims from request extensions
 pub fn extract_claims(request: &Request) -> Option<&Claims> {
     let claims = request.extensions().get::<Claims>();
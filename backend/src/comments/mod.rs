@@ -0,0 +1,464 @@
+pub mod store;
+
+use crate::auth::jwt::Claims;
+use crate::i18n::Locale;
+use crate::markdown::Post;
+use crate::spam::Label;
+use crate::utils::escape_html;
+use axum::{
+    extract::{Form, Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::{Html, Json},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use store::{Comment, CommentStatus};
+
+const MAX_BODY_CHARS: usize = 5000;
+const MAX_NAME_CHARS: usize = 80;
+/// Replies nested this deep get no reply form of their own
+const MAX_DEPTH: usize = 4;
+/// Response header that makes the moderation page reload its list
+const REFRESH_QUEUE: [(&str, &str); 1] = [("HX-Trigger", "comments-changed")];
+
+/// Whether readers who are not logged in may comment with just a name
+fn anonymous_allowed() -> bool {
+    std::env::var("BLOG_COMMENTS_ANONYMOUS").is_ok_and(|v| v == "true")
+}
+
+/// Called by `markdown::writer` when a post is deleted
+pub fn post_deleted(slug: &str) {
+    if let Err(e) = store::remove_for_post(slug) {
+        tracing::error!("Failed to remove comments of {}: {}", slug, e);
+    }
+}
+
+/// HTMX attributes that load the comment fragment, removed from static exports
+pub fn fragment_attributes(slug: &str) -> String {
+    format!(r#" hx-get="/posts/{}/comments" hx-trigger="load""#, slug)
+}
+
+/// Comment section under a post: approved comments, replaced by the fragment with its forms on load
+pub fn section(post: &Post, locale: &Locale) -> String {
+    if post.comments_closed {
+        return String::new();
+    }
+    format!(
+        r#"<section id="comments" class="comments"{}>{}</section>"#,
+        fragment_attributes(&post.slug),
+        render_thread(&post.slug, locale, None)
+    )
+}
+
+/// Comments are plain text; blank lines separate paragraphs
+fn format_body(body: &str) -> String {
+    body.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| format!("<p>{}</p>", escape_html(paragraph).replace('\n', "<br>")))
+        .collect()
+}
+
+/// Who the comment forms are shown to
+struct FormContext<'a> {
+    slug: &'a str,
+    /// Logged-in commenter, anonymous commenters are asked for a name
+    claims: Option<&'a Claims>,
+}
+
+fn comment_form(form: &FormContext, parent: Option<&str>, locale: &Locale) -> String {
+    let identity = match form.claims {
+        Some(claims) => format!(
+            r#"<p class="comment-identity">{}</p>"#,
+            escape_html(&locale.t("comments.posting_as").replace("{name}", &claims.display_name()))
+        ),
+        None => format!(
+            r#"<input type="text" name="name" required maxlength="{}" class="form-control" placeholder="{}">"#,
+            MAX_NAME_CHARS,
+            escape_html(&locale.t("comments.name"))
+        ),
+    };
+    let parent = parent.map_or(String::new(), |id| {
+        format!(r#"<input type="hidden" name="parent" value="{}">"#, escape_html(id))
+    });
+
+    // The form is only cleared once the comment was accepted
+    format!(
+        r#"<form class="comment-form" hx-post="/posts/{}/comments" hx-target="find .comment-status" hx-on::after-request="if (event.detail.xhr.status === 201) this.reset()">{}{}{}<textarea name="body" required maxlength="{}" rows="4" class="form-control" placeholder="{}"></textarea><button type="submit" class="btn btn-primary">{}</button><div class="comment-status"></div></form>"#,
+        form.slug,
+        parent,
+        crate::spam::form_fields(locale),
+        identity,
+        MAX_BODY_CHARS,
+        escape_html(&locale.t("comments.body")),
+        locale.t("comments.submit")
+    )
+}
+
+/// Nested list of the approved replies to `parent`, the top-level comments when it is `None`
+fn render_replies(
+    parent: Option<&str>,
+    depth: usize,
+    replies: &BTreeMap<Option<&str>, Vec<&Comment>>,
+    locale: &Locale,
+    form: Option<&FormContext>,
+) -> String {
+    let Some(children) = replies.get(&parent) else {
+        return String::new();
+    };
+
+    let items: String = children
+        .iter()
+        .map(|comment| {
+            let reply = form
+                .filter(|_| depth < MAX_DEPTH)
+                .map_or(String::new(), |form| {
+                    format!(
+                        r#"<details class="comment-reply"><summary>{}</summary>{}</details>"#,
+                        locale.t("comments.reply"),
+                        comment_form(form, Some(&comment.id), locale)
+                    )
+                });
+            format!(
+                r#"<li class="comment{}" id="comment-{}"><div class="comment-meta"><span class="comment-author">{}</span> <time datetime="{}">{}</time></div><div class="comment-body">{}</div>{}{}</li>"#,
+                if comment.author_sub.is_none() { " comment-anonymous" } else { "" },
+                comment.id,
+                escape_html(&comment.author_name),
+                comment.created_at.to_rfc3339(),
+                locale.format_date(&comment.created_at),
+                format_body(&comment.body),
+                reply,
+                render_replies(Some(&comment.id), depth + 1, replies, locale, form)
+            )
+        })
+        .collect();
+    format!(r#"<ol class="comment-list">{}</ol>"#, items)
+}
+
+/// Approved comments of a post, threaded; replies to comments that are not shown are left out
+fn render_thread(slug: &str, locale: &Locale, form: Option<&FormContext>) -> String {
+    let comments = store::comments_for(slug, CommentStatus::Approved);
+    let mut replies: BTreeMap<Option<&str>, Vec<&Comment>> = BTreeMap::new();
+    for comment in &comments {
+        replies.entry(comment.parent.as_deref()).or_default().push(comment);
+    }
+
+    let list = if replies.contains_key(&None) {
+        render_replies(None, 1, &replies, locale, form)
+    } else {
+        format!(r#"<p class="comments-empty">{}</p>"#, locale.t("comments.empty"))
+    };
+    format!("<h2>{}</h2>{}", locale.t("comments.heading"), list)
+}
+
+/// `GET /posts/{slug}/comments`: the thread, with forms for visitors who may comment
+pub async fn fragment(
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    let post = crate::markdown::reader::read_post(&slug).map_err(|_| StatusCode::NOT_FOUND)?;
+    if post.comments_closed {
+        return Ok(Html(format!(
+            r#"<p class="comments-closed">{}</p>"#,
+            locale.t("comments.closed")
+        )));
+    }
+
+    let claims = crate::auth::optional_claims(&headers).await;
+    let form = (claims.is_some() || anonymous_allowed()).then(|| FormContext {
+        slug: &post.slug,
+        claims: claims.as_ref(),
+    });
+    let new_comment = match &form {
+        Some(form) => comment_form(form, None, &locale),
+        None => format!(r#"<p class="comments-login">{}</p>"#, locale.t("comments.login_required")),
+    };
+    Ok(Html(format!(
+        "{}{}",
+        render_thread(&post.slug, &locale, form.as_ref()),
+        new_comment
+    )))
+}
+
+#[derive(Deserialize)]
+pub struct CommentForm {
+    body: String,
+    #[serde(default)]
+    name: String,
+    /// Id of the comment being replied to, empty for a top-level comment
+    #[serde(default)]
+    parent: String,
+    /// Honeypot, see `spam::HONEYPOT_FIELD`
+    #[serde(default)]
+    website: String,
+    /// Signed render time, see `spam::ISSUED_FIELD`
+    #[serde(default)]
+    issued: String,
+}
+
+fn message(class: &str, text: &str) -> Html<String> {
+    Html(format!(
+        r#"<p class="message {}">{}</p>"#,
+        class,
+        escape_html(text)
+    ))
+}
+
+/// `POST /posts/{slug}/comments`: queue a comment for moderation
+///
+/// Accepted comments answer 201 so the form knows to clear itself; problems are shown in the form.
+pub async fn submit(
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+    locale: Locale,
+    Form(form): Form<CommentForm>,
+) -> (StatusCode, Html<String>) {
+    let refuse = |key: &str| (StatusCode::OK, message("error", &locale.t(key)));
+
+    let Ok(post) = crate::markdown::reader::read_post(&slug) else {
+        return (StatusCode::NOT_FOUND, message("error", &locale.t("post.not_found")));
+    };
+    if post.comments_closed {
+        return refuse("comments.closed");
+    }
+
+    let claims = crate::auth::optional_claims(&headers).await;
+    let author_name = match &claims {
+        Some(claims) => claims.display_name(),
+        None if anonymous_allowed() => form.name.trim().to_string(),
+        None => return refuse("comments.login_required"),
+    };
+    if author_name.is_empty() {
+        return refuse("comments.name_required");
+    }
+    if author_name.chars().count() > MAX_NAME_CHARS {
+        return refuse("comments.name_too_long");
+    }
+
+    let body = form.body.replace("\r\n", "\n").trim().to_string();
+    if body.is_empty() {
+        return refuse("comments.body_required");
+    }
+    if body.chars().count() > MAX_BODY_CHARS {
+        return refuse("comments.body_too_long");
+    }
+
+    // Replies go under approved comments of the same post only
+    let parent = Some(form.parent.trim().to_string()).filter(|parent| !parent.is_empty());
+    if let Some(parent) = &parent {
+        let valid = matches!(
+            store::get(parent),
+            Ok(Some(comment)) if comment.slug == post.slug && comment.status == CommentStatus::Approved
+        );
+        if !valid {
+            return refuse("comments.invalid_parent");
+        }
+    }
+
+    let ip = crate::spam::client_ip(&headers);
+    let verdict = crate::spam::check(&crate::spam::Submission {
+        text: &format!("{}\n{}", author_name, body),
+        ip: ip.as_deref(),
+        form: Some(crate::spam::FormFields {
+            honeypot: &form.website,
+            issued: &form.issued,
+        }),
+    });
+
+    // Quarantined comments get the same answer, so spammers do not learn what gave them away
+    let comment = Comment {
+        id: uuid::Uuid::new_v4().to_string(),
+        slug: post.slug.clone(),
+        parent,
+        author_name,
+        author_sub: claims.map(|claims| claims.sub),
+        body,
+        status: if verdict.is_spam() {
+            CommentStatus::Spam
+        } else {
+            CommentStatus::Pending
+        },
+        created_at: chrono::Utc::now(),
+        moderated_at: None,
+        spam: Some(verdict),
+        trained_as: None,
+    };
+    match store::add(&comment) {
+        Ok(()) => {
+            if comment.status == CommentStatus::Pending {
+                crate::notifications::notify(
+                    crate::notifications::store::NotificationKind::CommentPending,
+                    &post,
+                    &comment.author_name,
+                );
+            }
+            (
+                StatusCode::CREATED,
+                message("success", &locale.t("comments.awaiting_moderation")),
+            )
+        }
+        Err(e) => {
+            tracing::error!("Failed to store comment on {}: {}", comment.slug, e);
+            (StatusCode::OK, message("error", &locale.t("comments.error")))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ModerationQuery {
+    status: Option<CommentStatus>,
+}
+
+/// `/admin/comments/queue`: comments for review, newest first, optionally filtered by status
+pub async fn list(Query(query): Query<ModerationQuery>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut comments: Vec<Comment> = store::comments()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .filter(|comment| query.status.is_none_or(|status| comment.status == status))
+        .collect();
+    comments.sort_by_key(|comment| std::cmp::Reverse(comment.created_at));
+    Ok(Json(json!({ "success": true, "comments": comments })))
+}
+
+/// `/admin/comments/queue/html`: table rows for the moderation page, pending comments by default
+pub async fn list_html(
+    locale: Locale,
+    Query(query): Query<ModerationQuery>,
+) -> Result<Html<String>, StatusCode> {
+    let status = query.status.unwrap_or(CommentStatus::Pending);
+    let mut comments: Vec<Comment> = store::comments()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .filter(|comment| comment.status == status)
+        .collect();
+    comments.sort_by_key(|comment| std::cmp::Reverse(comment.created_at));
+
+    if comments.is_empty() {
+        return Ok(Html(format!(
+            r#"<tr><td colspan="6" class="comments-empty">{}</td></tr>"#,
+            locale.t("comments.queue_empty")
+        )));
+    }
+
+    let titles: HashMap<String, String> = crate::markdown::reader::read_posts()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|post| (post.slug, post.title))
+        .collect();
+    let actions = [
+        ("approve", CommentStatus::Approved, "btn-primary"),
+        ("reject", CommentStatus::Rejected, "btn-secondary"),
+        ("spam", CommentStatus::Spam, "btn-secondary"),
+    ];
+
+    let rows: String = comments
+        .iter()
+        .map(|comment| {
+            let buttons: String = actions
+                .iter()
+                .filter(|(_, target, _)| *target != comment.status)
+                .map(|(action, _, class)| {
+                    format!(
+                        r#"<button class="btn {}" hx-post="/admin/comments/{}/{}" hx-swap="none">{}</button>"#,
+                        class,
+                        comment.id,
+                        action,
+                        locale.t(&format!("comments.{}", action))
+                    )
+                })
+                .collect();
+            let author = if comment.author_sub.is_some() {
+                escape_html(&comment.author_name)
+            } else {
+                format!(
+                    r#"{} <span class="comment-badge">{}</span>"#,
+                    escape_html(&comment.author_name),
+                    locale.t("comments.anonymous")
+                )
+            };
+            let score = comment.spam.as_ref().map_or(String::new(), |verdict| {
+                format!(
+                    r#"<span class="spam-score" title="{}">{:.0}%</span>"#,
+                    escape_html(&verdict.reasons.join(", ")),
+                    verdict.score * 100.0
+                )
+            });
+            format!(
+                r#"<tr><td><a href="/posts/{}">{}</a></td><td>{}</td><td class="comment-body">{}</td><td>{}</td><td><time datetime="{}">{}</time></td><td class="comment-actions">{}<button class="btn btn-danger" hx-delete="/admin/comments/{}" hx-swap="none" hx-confirm="{}">{}</button></td></tr>"#,
+                escape_html(&comment.slug),
+                escape_html(titles.get(&comment.slug).unwrap_or(&comment.slug)),
+                author,
+                format_body(&comment.body),
+                score,
+                comment.created_at.to_rfc3339(),
+                locale.format_date(&comment.created_at),
+                buttons,
+                comment.id,
+                escape_html(&locale.t("comments.confirm_delete")),
+                locale.t("comments.delete")
+            )
+        })
+        .collect();
+    Ok(Html(rows))
+}
+
+/// `/admin/comments`: the moderation page, its list is loaded with HTMX
+pub async fn serve_moderation_page(locale: Locale) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/admin/comments.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Html(locale.localize(&template)))
+}
+
+type ModerationResponse = ([(&'static str, &'static str); 1], Json<serde_json::Value>);
+
+/// Text the spam filter classifies and learns from
+fn spam_text(comment: &Comment) -> String {
+    format!("{}\n{}", comment.author_name, comment.body)
+}
+
+/// Approvals and spam reports train the spam filter; a rejection only takes back earlier training
+fn moderate(id: &str, status: CommentStatus) -> Result<ModerationResponse, StatusCode> {
+    let decision = match status {
+        CommentStatus::Approved => Some(Label::Ham),
+        CommentStatus::Spam => Some(Label::Spam),
+        CommentStatus::Pending | CommentStatus::Rejected => None,
+    };
+    let mut previous = None;
+    let result = store::update(id, |comment| {
+        previous = comment.trained_as;
+        comment.status = status;
+        comment.trained_as = decision;
+        comment.moderated_at = Some(chrono::Utc::now());
+    });
+    match result {
+        Ok(Some(comment)) => {
+            crate::spam::learn(&spam_text(&comment), previous, decision);
+            Ok((REFRESH_QUEUE, Json(json!({ "success": true, "comment": comment }))))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+pub async fn approve(Path(id): Path<String>) -> Result<ModerationResponse, StatusCode> {
+    moderate(&id, CommentStatus::Approved)
+}
+
+pub async fn reject(Path(id): Path<String>) -> Result<ModerationResponse, StatusCode> {
+    moderate(&id, CommentStatus::Rejected)
+}
+
+pub async fn mark_spam(Path(id): Path<String>) -> Result<ModerationResponse, StatusCode> {
+    moderate(&id, CommentStatus::Spam)
+}
+
+/// `DELETE /admin/comments/{id}`: remove a comment and the replies under it
+pub async fn delete(Path(id): Path<String>) -> Result<ModerationResponse, StatusCode> {
+    match store::remove(&id) {
+        Ok(true) => Ok((REFRESH_QUEUE, Json(json!({ "success": true })))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
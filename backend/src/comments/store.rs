@@ -0,0 +1,145 @@
+use crate::spam::{Label, Verdict};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const COMMENTS_FILE: &str = "comments.json";
+
+/// Where a comment stands in moderation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentStatus {
+    /// Waiting for an author to review it
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
+/// A reader comment on a post
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: String,
+    pub slug: String,
+    /// Comment this one replies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub author_name: String,
+    /// Keycloak subject of a logged-in commenter, none for anonymous comments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_sub: Option<String>,
+    pub body: String,
+    pub status: CommentStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderated_at: Option<DateTime<Utc>>,
+    /// What the spam filter made of the comment when it was submitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spam: Option<Verdict>,
+    /// Moderator decision the spam filter last learned from this comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trained_as: Option<Label>,
+}
+
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn read() -> Result<Vec<Comment>> {
+    let path = Path::new(COMMENTS_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read comments.json")?;
+    serde_json::from_str(&content).context("Failed to parse comments.json")
+}
+
+fn write(comments: &[Comment]) -> Result<()> {
+    let content = serde_json::to_string_pretty(comments).context("Failed to serialize comments")?;
+    std::fs::write(COMMENTS_FILE, content).context("Failed to write comments.json")
+}
+
+pub fn comments() -> Result<Vec<Comment>> {
+    let _guard = lock().lock().unwrap();
+    read()
+}
+
+/// Comments on a post with the given status, oldest first
+pub fn comments_for(slug: &str, status: CommentStatus) -> Vec<Comment> {
+    let mut comments: Vec<Comment> = comments()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|comment| comment.slug == slug && comment.status == status)
+        .collect();
+    comments.sort_by_key(|comment| comment.created_at);
+    comments
+}
+
+pub fn get(id: &str) -> Result<Option<Comment>> {
+    Ok(comments()?.into_iter().find(|comment| comment.id == id))
+}
+
+pub fn add(comment: &Comment) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut comments = read()?;
+    comments.push(comment.clone());
+    write(&comments)
+}
+
+/// Apply a change to one comment, returning it if it exists
+pub fn update(id: &str, change: impl FnOnce(&mut Comment)) -> Result<Option<Comment>> {
+    let _guard = lock().lock().unwrap();
+    let mut comments = read()?;
+    let updated = comments.iter_mut().find(|comment| comment.id == id).map(|comment| {
+        change(comment);
+        comment.clone()
+    });
+    if updated.is_some() {
+        write(&comments)?;
+    }
+    Ok(updated)
+}
+
+/// Remove a comment together with the replies under it
+pub fn remove(id: &str) -> Result<bool> {
+    let _guard = lock().lock().unwrap();
+    let mut comments = read()?;
+
+    let mut removed = BTreeSet::from([id.to_string()]);
+    // Replies are always newer than their parent, but the file is not guaranteed to be ordered
+    loop {
+        let before = removed.len();
+        for comment in &comments {
+            if comment.parent.as_ref().is_some_and(|parent| removed.contains(parent)) {
+                removed.insert(comment.id.clone());
+            }
+        }
+        if removed.len() == before {
+            break;
+        }
+    }
+
+    let before = comments.len();
+    comments.retain(|comment| !removed.contains(&comment.id));
+    let changed = comments.len() != before;
+    if changed {
+        write(&comments)?;
+    }
+    Ok(changed)
+}
+
+/// Remove every comment on a post
+pub fn remove_for_post(slug: &str) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut comments = read()?;
+    let before = comments.len();
+    comments.retain(|comment| comment.slug != slug);
+    if comments.len() != before {
+        write(&comments)?;
+    }
+    Ok(())
+}
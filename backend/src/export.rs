@@ -0,0 +1,331 @@
+use crate::markdown::Post;
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tower::Service;
+
+const MANIFEST_FILE: &str = ".export-manifest.json";
+const POST_TEMPLATE: &str = "../frontend/templates/post.html";
+const STATIC_DIR: &str = "../frontend/static";
+/// HTMX attributes that refresh server-rendered listings
+const FRAGMENT_ATTRIBUTES: [&str; 3] = [
+    r#" hx-get="/posts/html" hx-trigger="load""#,
+    r#" hx-get="/archive/html" hx-trigger="load""#,
+    r#" hx-get="/archive/widget" hx-trigger="load""#,
+];
+
+/// What the previous export produced, used to skip unchanged posts and prune stale files
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Content hash of each exported post, by slug
+    posts: BTreeMap<String, String>,
+    /// Every file written by the export, relative to the output directory
+    files: BTreeSet<String>,
+}
+
+impl Manifest {
+    fn load(out_dir: &Path) -> Self {
+        std::fs::read_to_string(out_dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Base URL the mirror is served from, defaults to the public URL of the blog
+pub fn export_base_url() -> String {
+    std::env::var("BLOG_EXPORT_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(crate::utils::public_base_url)
+}
+
+/// A page to write: the route it is rendered from and the file it is stored in
+struct Page {
+    route: String,
+    file: String,
+}
+
+impl Page {
+    fn new(route: impl Into<String>, file: impl Into<String>) -> Self {
+        Self {
+            route: route.into(),
+            file: file.into(),
+        }
+    }
+}
+
+/// Hash of everything that goes into a post page, so unchanged posts are not re-rendered
+fn post_hash(post: &Post, template: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(crate::markdown::cache::RENDER_OPTIONS.as_bytes());
+    hasher.update(export_base_url().as_bytes());
+    hasher.update(template.as_bytes());
+    hasher.update(serde_json::to_vec(post).unwrap_or_default());
+    // The language switcher and alternate links depend on the other translations
+    for translation in crate::markdown::reader::read_translations(post).unwrap_or_default() {
+        hasher.update(translation.slug.as_bytes());
+        hasher.update(translation.lang.as_bytes());
+        hasher.update(translation.title.as_bytes());
+    }
+    // Approved webmentions are listed under the post
+    for mention in crate::webmention::store::mentions_for(&post.slug, crate::webmention::store::MentionStatus::Approved) {
+        hasher.update(mention.source.as_bytes());
+        hasher.update(mention.title.unwrap_or_default().as_bytes());
+    }
+    // So are approved comments
+    for comment in crate::comments::store::comments_for(&post.slug, crate::comments::store::CommentStatus::Approved) {
+        hasher.update(comment.id.as_bytes());
+        hasher.update(comment.body.as_bytes());
+    }
+    // And reaction counts
+    for (kind, count) in crate::reactions::store::counts(&post.slug) {
+        hasher.update(kind.name().as_bytes());
+        hasher.update(count.to_le_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Render a route through the application, `None` when it does not exist
+async fn fetch(app: &Router, route: &str) -> Result<Option<Vec<u8>>> {
+    let request = Request::builder()
+        .uri(route)
+        .body(Body::empty())
+        .context("Failed to build export request")?;
+    // The router is always ready, so it can be called without polling readiness
+    let response = app
+        .clone()
+        .call(request)
+        .await
+        .map_err(|e| anyhow!("Failed to render {}: {}", route, e))?;
+
+    match response.status() {
+        StatusCode::OK => {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|e| anyhow!("Failed to read {}: {}", route, e))?;
+            Ok(Some(body.to_vec()))
+        }
+        StatusCode::NOT_FOUND => Ok(None),
+        status => Err(anyhow!("Rendering {} returned {}", route, status)),
+    }
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~' | '%' | '/')
+}
+
+/// Replace a route with its exported path wherever it appears as a whole path
+fn replace_route(body: &str, from: &str, to: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(index) = rest.find(from) {
+        let after = &rest[index + from.len()..];
+        out.push_str(&rest[..index]);
+        if after.chars().next().is_none_or(|c| !is_path_char(c)) {
+            out.push_str(to);
+        } else {
+            out.push_str(from);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Point links at the exported files and the mirror's base URL
+fn rewrite_urls(body: &str, rewrites: &[(String, String)]) -> String {
+    let mut body = rewrites
+        .iter()
+        .fold(body.to_string(), |body, (from, to)| replace_route(&body, from, to));
+
+    let public_url = crate::utils::public_base_url();
+    let export_url = export_base_url();
+    if public_url != export_url {
+        body = body.replace(&public_url, &export_url);
+    }
+    body
+}
+
+/// Write a file unless it already has the same content, so object storage syncs stay small
+fn write_file(out_dir: &Path, file: &str, content: &[u8]) -> Result<()> {
+    let path = out_dir.join(file);
+    if std::fs::read(&path).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Export the public site as static files into `out_dir`
+pub async fn run(app: Router, out_dir: &Path) -> Result<()> {
+    let previous = Manifest::load(out_dir);
+    let mut manifest = Manifest::default();
+
+    let mut posts = crate::markdown::reader::read_posts()?;
+    posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    let template = std::fs::read_to_string(POST_TEMPLATE).context("Failed to read post template")?;
+
+    let tags: BTreeSet<String> = posts.iter().flat_map(|post| post.tags.clone()).collect();
+    let authors: BTreeSet<String> = posts.iter().map(|post| post.author.clone()).collect();
+
+    let mut pages = vec![
+        Page::new("/", "index.html"),
+        Page::new("/feed.xml", "feed.xml"),
+        Page::new("/atom.xml", "atom.xml"),
+        Page::new("/robots.txt", "robots.txt"),
+        Page::new("/sitemap.xml", "sitemap.xml"),
+        Page::new("/archive", "archive.html"),
+    ];
+    let mut rewrites: Vec<(String, String)> = vec![("/archive".to_string(), "/archive.html".to_string())];
+
+    let months: BTreeSet<crate::archive::Period> = posts.iter().map(crate::archive::Period::month_of).collect();
+    let years: BTreeSet<crate::archive::Period> = months
+        .iter()
+        .map(|period| crate::archive::Period {
+            year: period.year,
+            month: None,
+        })
+        .collect();
+    for period in years.iter().chain(months.iter()) {
+        let route = period.path();
+        rewrites.push((route.clone(), format!("{}.html", route)));
+        pages.push(Page::new(route.clone(), format!("{}.html", &route[1..])));
+    }
+
+    for post in &posts {
+        let route = format!("/posts/{}", post.slug);
+        rewrites.push((route.clone(), format!("{}.html", route)));
+    }
+    for tag in &tags {
+        let route = format!("/tags/{}", urlencoding::encode(tag));
+        rewrites.push((route.clone(), format!("{}.html", route)));
+        pages.push(Page::new(route.clone(), format!("{}.html", &route[1..])));
+        pages.push(Page::new(format!("{}/feed.xml", route), format!("{}/feed.xml", &route[1..])));
+        pages.push(Page::new(format!("{}/atom.xml", route), format!("{}/atom.xml", &route[1..])));
+    }
+    for author in &authors {
+        let route = format!("/authors/{}", urlencoding::encode(author));
+        pages.push(Page::new(format!("{}/feed.xml", route), format!("{}/feed.xml", &route[1..])));
+        pages.push(Page::new(format!("{}/atom.xml", route), format!("{}/atom.xml", &route[1..])));
+    }
+
+    // Query strings cannot be served from object storage, so feed pages get their own files
+    let mut rendered: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut page_number = 1;
+    while let Some(body) = fetch(&app, &format!("/feed.json?page={}", page_number)).await? {
+        let file = if page_number == 1 {
+            "feed.json".to_string()
+        } else {
+            format!("feed-{}.json", page_number)
+        };
+        rewrites.push((format!("/feed.json?page={}", page_number), format!("/{}", file)));
+        rendered.push((file, body));
+        page_number += 1;
+    }
+
+    for page in &pages {
+        if let Some(body) = fetch(&app, &page.route).await? {
+            rendered.push((page.file.clone(), body));
+        }
+    }
+
+    // A large sitemap is split into chunks listed by an index
+    let is_index = rendered
+        .iter()
+        .any(|(file, body)| file == "sitemap.xml" && String::from_utf8_lossy(body).contains("<sitemapindex"));
+    if is_index {
+        let mut chunk = 1;
+        while let Some(body) = fetch(&app, &format!("/sitemaps/{}.xml", chunk)).await? {
+            rendered.push((format!("sitemaps/{}.xml", chunk), body));
+            chunk += 1;
+        }
+    }
+
+    let mut rendered_posts = 0;
+    for post in &posts {
+        let file = format!("posts/{}.html", post.slug);
+        let hash = post_hash(post, &template);
+        manifest.posts.insert(post.slug.clone(), hash.clone());
+        manifest.files.insert(file.clone());
+
+        let unchanged = previous.posts.get(&post.slug) == Some(&hash) && out_dir.join(&file).exists();
+        if unchanged {
+            continue;
+        }
+
+        let body = fetch(&app, &format!("/posts/{}", post.slug))
+            .await?
+            .ok_or_else(|| anyhow!("Post {} could not be rendered", post.slug))?;
+        // The mirror shows approved comments and reaction counts, it cannot take new ones
+        let body = String::from_utf8_lossy(&body)
+            .replace(&crate::comments::fragment_attributes(&post.slug), "")
+            .replace(&crate::reactions::fragment_attributes(&post.slug), "");
+        rendered.push((file, body.into_bytes()));
+        rendered_posts += 1;
+    }
+
+    for (file, body) in rendered {
+        let body = rewrite_urls(&String::from_utf8_lossy(&body), &rewrites);
+        // Listings are part of the page already, there are no fragment endpoints to load them from
+        let body = FRAGMENT_ATTRIBUTES
+            .iter()
+            .fold(body, |body, attributes| body.replace(attributes, ""));
+        write_file(out_dir, &file, body.as_bytes())?;
+        manifest.files.insert(file);
+    }
+
+    for entry in std::fs::read_dir(STATIC_DIR).context("Failed to read static assets")? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            let file = format!("static/{}", entry.file_name().to_string_lossy());
+            write_file(out_dir, &file, &std::fs::read(entry.path())?)?;
+            manifest.files.insert(file);
+        }
+    }
+
+    // Uploads keep their names, posts link to them as they are
+    crate::media::variants::generate_all()?;
+    if let Ok(entries) = std::fs::read_dir(crate::media::store::media_dir()) {
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_file() && crate::media::store::is_valid_name(&name) {
+                let file = format!("media/{}", name);
+                write_file(out_dir, &file, &std::fs::read(entry.path())?)?;
+                manifest.files.insert(file);
+            }
+        }
+    }
+
+    // Remove what earlier exports wrote for deleted posts, tags and authors
+    for stale in previous.files.difference(&manifest.files) {
+        let _ = std::fs::remove_file(out_dir.join(stale));
+    }
+
+    write_file(
+        out_dir,
+        MANIFEST_FILE,
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+
+    println!(
+        "📦 Exported {} posts to {} ({} re-rendered)",
+        posts.len(),
+        out_dir.display(),
+        rendered_posts
+    );
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::feeds::{cover_enclosure, last_modified, post_html, post_url, FeedScope};
+use crate::markdown::Post;
+use crate::utils::escape_html;
+
+/// Render an Atom 1.0 feed
+pub fn render(scope: &FeedScope, posts: &[Post]) -> String {
+    let base_url = crate::utils::public_base_url();
+    let self_url = format!("{}{}/atom.xml", base_url, scope.path_prefix());
+
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    xml.push_str(&format!("<id>{}</id>", escape_html(&self_url)));
+    xml.push_str(&format!("<title>{}</title>", escape_html(&scope.title())));
+    xml.push_str(&format!(
+        "<updated>{}</updated>",
+        last_modified(posts).to_rfc3339()
+    ));
+    xml.push_str(&format!(
+        r#"<link rel="self" type="application/atom+xml" href="{}"/>"#,
+        escape_html(&self_url)
+    ));
+    xml.push_str(&format!(
+        r#"<link rel="alternate" type="text/html" href="{}/"/>"#,
+        escape_html(&base_url)
+    ));
+
+    for post in posts {
+        let url = escape_html(&post_url(post));
+        xml.push_str("<entry>");
+        xml.push_str(&format!("<id>{}</id>", url));
+        xml.push_str(&format!("<title>{}</title>", escape_html(&post.title)));
+        xml.push_str(&format!(
+            r#"<link rel="alternate" type="text/html" href="{}"/>"#,
+            url
+        ));
+        xml.push_str(&format!(
+            "<published>{}</published>",
+            post.created_at.to_rfc3339()
+        ));
+        xml.push_str(&format!("<updated>{}</updated>", post.updated_at.to_rfc3339()));
+        xml.push_str(&format!(
+            "<author><name>{}</name></author>",
+            escape_html(&post.author)
+        ));
+        for tag in &post.tags {
+            xml.push_str(&format!(r#"<category term="{}"/>"#, escape_html(tag)));
+        }
+        if let Some((cover, size, mime)) = cover_enclosure(post) {
+            xml.push_str(&format!(
+                r#"<link rel="enclosure" type="{}" length="{}" href="{}"/>"#,
+                mime,
+                size,
+                escape_html(&cover)
+            ));
+        }
+        xml.push_str(&format!(
+            r#"<content type="html" xml:lang="{}">{}</content>"#,
+            escape_html(&post.lang),
+            escape_html(&post_html(post))
+        ));
+        xml.push_str("</entry>");
+    }
+
+    xml.push_str("</feed>");
+    xml
+}
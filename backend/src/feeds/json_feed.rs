@@ -0,0 +1,143 @@
+use crate::feeds::{conditional_response, last_modified, post_html, post_url, scoped_posts, FeedScope};
+use crate::markdown::Post;
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const PAGE_SIZE: usize = 20;
+const SUMMARY_CHARS: usize = 280;
+const CONTENT_TYPE: &str = "application/feed+json; charset=utf-8";
+
+/// JSON Feed 1.1 document, see https://www.jsonfeed.org/version/1.1/
+#[derive(Debug, Serialize)]
+pub struct JsonFeed {
+    pub version: &'static str,
+    pub title: String,
+    pub home_page_url: String,
+    pub feed_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_url: Option<String>,
+    pub items: Vec<JsonFeedItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonFeedItem {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub content_html: String,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub date_published: DateTime<Utc>,
+    pub date_modified: DateTime<Utc>,
+    pub authors: Vec<JsonFeedAuthor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub language: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonFeedAuthor {
+    pub name: String,
+}
+
+impl From<&Post> for JsonFeedItem {
+    fn from(post: &Post) -> Self {
+        let url = post_url(post);
+        Self {
+            id: url.clone(),
+            url,
+            title: post.title.clone(),
+            content_html: post_html(post),
+            summary: crate::markdown::reader::excerpt(&post.content, SUMMARY_CHARS),
+            image: post
+                .cover
+                .as_ref()
+                .map(|cover| crate::seo::meta::absolute_url(&cover.url)),
+            date_published: post.created_at,
+            date_modified: post.updated_at,
+            authors: vec![JsonFeedAuthor {
+                name: post.author.clone(),
+            }],
+            tags: post.tags.clone(),
+            language: post.lang.clone(),
+        }
+    }
+}
+
+/// Index of the first post on a page, none for page 0 or a page too far out to count to
+fn page_start(page: usize) -> Option<usize> {
+    page.checked_sub(1)?.checked_mul(PAGE_SIZE)
+}
+
+/// Build one page of the feed; pages are numbered from 1, and none exist past the last post
+pub fn build(scope: &FeedScope, posts: &[Post], page: usize) -> Option<JsonFeed> {
+    let start = page_start(page).filter(|start| page == 1 || *start < posts.len())?;
+    let base_url = crate::utils::public_base_url();
+    let feed_url = format!("{}{}/feed.json", base_url, scope.path_prefix());
+
+    let next_url = (posts.len() > start + PAGE_SIZE)
+        .then(|| format!("{}?page={}", feed_url, page + 1));
+    let feed_url = if page > 1 {
+        format!("{}?page={}", feed_url, page)
+    } else {
+        feed_url
+    };
+
+    Some(JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: scope.title(),
+        home_page_url: format!("{}/", base_url),
+        feed_url,
+        next_url,
+        items: posts
+            .iter()
+            .skip(start)
+            .take(PAGE_SIZE)
+            .map(JsonFeedItem::from)
+            .collect(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    page: Option<usize>,
+}
+
+pub async fn json_feed(
+    Query(query): Query<PageQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let scope = FeedScope::All;
+    let posts = scoped_posts(&scope).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let feed = build(&scope, &posts, page).ok_or(StatusCode::NOT_FOUND)?;
+    let body = serde_json::to_string(&feed).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(conditional_response(
+        &headers,
+        body,
+        CONTENT_TYPE,
+        last_modified(&posts),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_past_the_last_post_do_not_exist() {
+        assert!(build(&FeedScope::All, &[], 1).is_some_and(|feed| feed.items.is_empty()));
+        assert!(build(&FeedScope::All, &[], 2).is_none());
+        assert!(build(&FeedScope::All, &[], usize::MAX).is_none());
+    }
+}
@@ -0,0 +1,206 @@
+pub mod atom;
+pub mod json_feed;
+pub mod rss;
+
+use crate::markdown::Post;
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// Maximum number of entries in a feed
+const FEED_LIMIT: usize = 50;
+
+/// Which posts a feed covers
+#[derive(Debug, Clone)]
+pub enum FeedScope {
+    All,
+    Tag(String),
+    Author(String),
+}
+
+impl FeedScope {
+    fn includes(&self, post: &Post) -> bool {
+        match self {
+            FeedScope::All => true,
+            FeedScope::Tag(tag) => post.has_tag(tag),
+            FeedScope::Author(author) => &post.author == author,
+        }
+    }
+
+    /// Path prefix of this scope's feeds, e.g. `/tags/rust`
+    pub fn path_prefix(&self) -> String {
+        match self {
+            FeedScope::All => String::new(),
+            FeedScope::Tag(tag) => format!("/tags/{}", urlencoding::encode(tag)),
+            FeedScope::Author(author) => format!("/authors/{}", urlencoding::encode(author)),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let site = crate::utils::site_title();
+        match self {
+            FeedScope::All => site,
+            FeedScope::Tag(tag) => format!("{} - #{}", site, tag),
+            FeedScope::Author(author) => format!("{} - {}", site, author),
+        }
+    }
+}
+
+/// All published posts in a scope, most recent first
+pub fn scoped_posts(scope: &FeedScope) -> Result<Vec<Post>> {
+    let mut posts: Vec<Post> = crate::markdown::reader::read_posts()?
+        .into_iter()
+        .filter(|post| scope.includes(post))
+        .collect();
+    posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    Ok(posts)
+}
+
+/// The most recent published posts in a scope
+pub fn feed_posts(scope: &FeedScope) -> Result<Vec<Post>> {
+    let mut posts = scoped_posts(scope)?;
+    posts.truncate(FEED_LIMIT);
+    Ok(posts)
+}
+
+/// Most recent modification among the posts, used for Last-Modified and feed timestamps
+pub fn last_modified(posts: &[Post]) -> DateTime<Utc> {
+    posts
+        .iter()
+        .map(|post| post.updated_at)
+        .max()
+        .unwrap_or(DateTime::<Utc>::UNIX_EPOCH)
+}
+
+/// Rendered HTML of a post for inclusion in a feed
+pub fn post_html(post: &Post) -> String {
+    crate::markdown::cache::render_post(&post.slug)
+        .unwrap_or_else(|_| crate::markdown::cache::render(&post.content))
+}
+
+/// Cover image of a post as an enclosure: absolute URL, size in bytes and MIME type
+pub fn cover_enclosure(post: &Post) -> Option<(String, u64, &'static str)> {
+    let cover = post.cover.as_ref()?;
+    let (size, mime) = crate::media::file_info(&cover.url)?;
+    Some((crate::seo::meta::absolute_url(&cover.url), size, mime))
+}
+
+pub fn post_url(post: &Post) -> String {
+    format!("{}/posts/{}", crate::utils::public_base_url(), post.slug)
+}
+
+fn http_date(date: &DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Build a response honouring If-None-Match and If-Modified-Since
+pub fn conditional_response(
+    headers: &HeaderMap,
+    body: String,
+    content_type: &str,
+    last_modified: DateTime<Utc>,
+) -> Response {
+    let etag = format!("\"{:x}\"", Sha256::digest(body.as_bytes()));
+    // HTTP dates have no sub-second precision
+    let last_modified = DateTime::<Utc>::from_timestamp(last_modified.timestamp(), 0)
+        .unwrap_or(last_modified);
+
+    let not_modified = match headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+    {
+        Some(if_none_match) => if_none_match
+            .split(',')
+            .any(|tag| tag.trim() == etag || tag.trim() == "*"),
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| last_modified <= since),
+    };
+
+    let builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::LAST_MODIFIED, http_date(&last_modified))
+        .header(header::CACHE_CONTROL, "public, max-age=300");
+
+    let response = if not_modified {
+        builder.status(StatusCode::NOT_MODIFIED).body(Body::empty())
+    } else {
+        builder
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+    };
+
+    response.unwrap_or_else(|_| {
+        Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::empty())
+            .unwrap()
+    })
+}
+
+fn feed_response(
+    headers: &HeaderMap,
+    scope: FeedScope,
+    content_type: &str,
+    render: fn(&FeedScope, &[Post]) -> String,
+) -> Result<Response, StatusCode> {
+    let posts = feed_posts(&scope).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if posts.is_empty() && !matches!(scope, FeedScope::All) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let body = render(&scope, &posts);
+    Ok(conditional_response(
+        headers,
+        body,
+        content_type,
+        last_modified(&posts),
+    ))
+}
+
+const RSS_CONTENT_TYPE: &str = "application/rss+xml; charset=utf-8";
+const ATOM_CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+pub async fn rss_feed(headers: HeaderMap) -> Result<Response, StatusCode> {
+    feed_response(&headers, FeedScope::All, RSS_CONTENT_TYPE, rss::render)
+}
+
+pub async fn atom_feed(headers: HeaderMap) -> Result<Response, StatusCode> {
+    feed_response(&headers, FeedScope::All, ATOM_CONTENT_TYPE, atom::render)
+}
+
+pub async fn tag_rss_feed(
+    Path(tag): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    feed_response(&headers, FeedScope::Tag(tag), RSS_CONTENT_TYPE, rss::render)
+}
+
+pub async fn tag_atom_feed(
+    Path(tag): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    feed_response(&headers, FeedScope::Tag(tag), ATOM_CONTENT_TYPE, atom::render)
+}
+
+pub async fn author_rss_feed(
+    Path(author): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    feed_response(&headers, FeedScope::Author(author), RSS_CONTENT_TYPE, rss::render)
+}
+
+pub async fn author_atom_feed(
+    Path(author): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    feed_response(&headers, FeedScope::Author(author), ATOM_CONTENT_TYPE, atom::render)
+}
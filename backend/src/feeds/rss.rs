@@ -0,0 +1,70 @@
+use crate::feeds::{cover_enclosure, last_modified, post_html, post_url, FeedScope};
+use crate::markdown::Post;
+use crate::utils::escape_html;
+
+/// Wrap HTML in a CDATA section, splitting any `]]>` it contains
+fn cdata(html: &str) -> String {
+    format!("<![CDATA[{}]]>", html.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Render an RSS 2.0 feed
+pub fn render(scope: &FeedScope, posts: &[Post]) -> String {
+    let base_url = crate::utils::public_base_url();
+    let self_url = format!("{}{}/feed.xml", base_url, scope.path_prefix());
+
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">"#,
+    );
+    xml.push_str("<channel>");
+    xml.push_str(&format!("<title>{}</title>", escape_html(&scope.title())));
+    xml.push_str(&format!("<link>{}/</link>", escape_html(&base_url)));
+    xml.push_str(&format!(
+        "<description>{}</description>",
+        escape_html(&scope.title())
+    ));
+    xml.push_str(&format!(
+        r#"<atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        escape_html(&self_url)
+    ));
+    xml.push_str(&format!(
+        "<lastBuildDate>{}</lastBuildDate>",
+        last_modified(posts).to_rfc2822()
+    ));
+
+    for post in posts {
+        let url = escape_html(&post_url(post));
+        xml.push_str("<item>");
+        xml.push_str(&format!("<title>{}</title>", escape_html(&post.title)));
+        xml.push_str(&format!("<link>{}</link>", url));
+        xml.push_str(&format!(r#"<guid isPermaLink="true">{}</guid>"#, url));
+        xml.push_str(&format!("<pubDate>{}</pubDate>", post.created_at.to_rfc2822()));
+        xml.push_str(&format!(
+            "<atom:updated>{}</atom:updated>",
+            post.updated_at.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            escape_html(&post.author)
+        ));
+        for tag in &post.tags {
+            xml.push_str(&format!("<category>{}</category>", escape_html(tag)));
+        }
+        if let Some((cover, size, mime)) = cover_enclosure(post) {
+            xml.push_str(&format!(
+                r#"<enclosure url="{}" length="{}" type="{}"/>"#,
+                escape_html(&cover),
+                size,
+                mime
+            ));
+        }
+        xml.push_str(&format!(
+            "<content:encoded>{}</content:encoded>",
+            cdata(&post_html(post))
+        ));
+        xml.push_str("</item>");
+    }
+
+    xml.push_str("</channel></rss>");
+    xml
+}
@@ -0,0 +1,225 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::{header, Url};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Pages larger than this are cut off, what we look for is expected near the start anyway
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const MAX_REDIRECTS: usize = 5;
+
+/// A fetched remote page
+#[derive(Debug, Clone, Default)]
+pub struct FetchedPage {
+    pub status: u16,
+    /// Values of every `Link` response header
+    pub link_headers: Vec<String>,
+    pub body: String,
+}
+
+/// Outgoing requests to URLs chosen by other sites, such as webmention sources and ActivityPub actors
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// GET a page, following redirects
+    async fn get(&self, url: &str, accept: &str) -> Result<FetchedPage>;
+
+    /// POST a body and return the response status; redirects are not followed
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16>;
+}
+
+/// Fetcher used outside of tests
+///
+/// The URLs come from whoever sent a mention or an activity, so only hosts on the public
+/// internet are contacted: each host is resolved and checked before connecting, the connection
+/// is pinned to the checked addresses, and redirects are followed one hop at a time under the
+/// same rules.
+pub struct HttpFetcher {
+    user_agent: String,
+}
+
+impl HttpFetcher {
+    fn new() -> Self {
+        Self {
+            user_agent: format!(
+                "blog-app/{} (+{})",
+                env!("CARGO_PKG_VERSION"),
+                crate::utils::public_base_url()
+            ),
+        }
+    }
+
+    /// A client that only connects to the public addresses of the host of `url`
+    async fn client_for(&self, url: &Url) -> Result<reqwest::Client> {
+        let addrs = public_addrs(url).await?;
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(self.user_agent.as_str());
+        if let Some(domain) = url.domain() {
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+        builder.build().context("Failed to build HTTP client")
+    }
+}
+
+#[async_trait]
+impl Fetcher for HttpFetcher {
+    async fn get(&self, url: &str, accept: &str) -> Result<FetchedPage> {
+        let mut url = parse_url(url)?;
+        let mut redirects = 0;
+        let mut response = loop {
+            let response = self
+                .client_for(&url)
+                .await?
+                .get(url.clone())
+                .header(header::ACCEPT, accept)
+                .send()
+                .await
+                .context(format!("Failed to fetch {}", url))?;
+            let location = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|value| value.to_str().ok());
+            let next = match location {
+                Some(location) if response.status().is_redirection() => location,
+                _ => break response,
+            };
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                bail!("Too many redirects fetching {}", url);
+            }
+            url = url
+                .join(next)
+                .context(format!("Invalid redirect from {}", url))?;
+            check_scheme(&url)?;
+        };
+
+        let status = response.status().as_u16();
+        let link_headers = response
+            .headers()
+            .get_all("link")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(String::from)
+            .collect();
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.context("Failed to read response")? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_BYTES {
+                body.truncate(MAX_BODY_BYTES);
+                break;
+            }
+        }
+
+        Ok(FetchedPage {
+            status,
+            link_headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16> {
+        let url = parse_url(url)?;
+        let mut request = self.client_for(&url).await?.post(url.clone());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .context(format!("Failed to post to {}", url))?;
+        Ok(response.status().as_u16())
+    }
+}
+
+fn check_scheme(url: &Url) -> Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("Only http(s) URLs can be fetched: {}", url);
+    }
+    Ok(())
+}
+
+fn parse_url(url: &str) -> Result<Url> {
+    let url = Url::parse(url).context(format!("Invalid URL {}", url))?;
+    check_scheme(&url)?;
+    Ok(url)
+}
+
+/// Whether an address belongs to the public internet rather than to the blog's own host or network
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space used by carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                // Benchmarking
+                || (a == 198 && (b == 18 || b == 19))
+                // Reserved
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let segments = ip.segments();
+            // NAT64 reaches IPv4 addresses through the embedded one
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public(IpAddr::from([a, b, c, d]));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link-local
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation
+                || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+        }
+    }
+}
+
+/// Resolve the host of `url`, refusing it unless every address is public
+async fn public_addrs(url: &Url) -> Result<Vec<SocketAddr>> {
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("No port for {}", url))?;
+    let host = url.host_str().ok_or_else(|| anyhow!("No host in {}", url))?;
+    let addrs: Vec<SocketAddr> = match url.domain() {
+        Some(domain) => tokio::net::lookup_host((domain, port))
+            .await
+            .context(format!("Failed to resolve {}", domain))?
+            .collect(),
+        // The host is an IP address
+        None => {
+            let ip: IpAddr = host.trim_start_matches('[').trim_end_matches(']').parse()?;
+            vec![SocketAddr::new(ip, port)]
+        }
+    };
+    if addrs.is_empty() {
+        bail!("{} did not resolve to any address", url);
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        bail!("Refusing to fetch {}: {} is not a public address", url, addr.ip());
+    }
+    Ok(addrs)
+}
+
+pub fn fetcher() -> &'static dyn Fetcher {
+    static FETCHER: OnceLock<HttpFetcher> = OnceLock::new();
+    FETCHER.get_or_init(HttpFetcher::new)
+}
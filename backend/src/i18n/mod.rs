@@ -0,0 +1,190 @@
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::{header, request::Parts, HeaderMap},
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{DateTime, Datelike, Utc};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::OnceLock;
+
+pub const DEFAULT_LOCALE: &str = "en";
+const LOCALE_COOKIE: &str = "lang";
+
+/// Translation catalogs compiled into the binary, keyed by locale
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.json")),
+    ("fr", include_str!("locales/fr.json")),
+];
+
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    static LOADED: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+    LOADED.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(locale, source)| {
+                let messages = serde_json::from_str(source)
+                    .unwrap_or_else(|e| panic!("Invalid {} translation catalog: {}", locale, e));
+                (*locale, messages)
+            })
+            .collect()
+    })
+}
+
+/// Locales with a translation catalog
+pub fn supported_locales() -> impl Iterator<Item = &'static str> {
+    CATALOGS.iter().map(|(locale, _)| *locale)
+}
+
+/// Map a language tag such as `fr-CA` onto a supported locale
+pub fn normalize(tag: &str) -> Option<&'static str> {
+    let language = tag.trim().split(['-', '_']).next()?.to_lowercase();
+    supported_locales().find(|locale| *locale == language)
+}
+
+/// Locale configured with BLOG_DEFAULT_LOCALE, falling back to English
+pub fn default_locale() -> &'static str {
+    std::env::var("BLOG_DEFAULT_LOCALE")
+        .ok()
+        .and_then(|tag| normalize(&tag))
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// The reader's locale, chosen from the `lang` cookie, then Accept-Language, then configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale(pub &'static str);
+
+impl Locale {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let from_cookie = headers
+            .get(header::COOKIE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|cookies| {
+                cookies
+                    .split(';')
+                    .map(|c| c.trim())
+                    .find_map(|c| c.strip_prefix("lang="))
+                    .and_then(normalize)
+            });
+
+        let from_accept_language = || {
+            let header = headers
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|h| h.to_str().ok())?;
+            let mut ranges: Vec<(&str, f32)> = header
+                .split(',')
+                .map(|range| {
+                    let mut parts = range.split(';');
+                    let tag = parts.next().unwrap_or_default().trim();
+                    let quality = parts
+                        .find_map(|p| p.trim().strip_prefix("q="))
+                        .and_then(|q| q.parse().ok())
+                        .unwrap_or(1.0);
+                    (tag, quality)
+                })
+                .collect();
+            ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+            ranges.into_iter().find_map(|(tag, _)| normalize(tag))
+        };
+
+        Locale(
+            from_cookie
+                .or_else(from_accept_language)
+                .unwrap_or_else(default_locale),
+        )
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.0
+    }
+
+    /// Translate a message key, falling back to the default catalog and then the key itself
+    pub fn t(&self, key: &str) -> String {
+        let catalogs = catalogs();
+        catalogs
+            .get(self.0)
+            .and_then(|messages| messages.get(key))
+            .or_else(|| catalogs.get(DEFAULT_LOCALE)?.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Format a date using the locale's pattern and month names
+    pub fn format_date(&self, date: &DateTime<Utc>) -> String {
+        self.t("date.format")
+            .replace("{month}", &self.t(&format!("month.{}", date.month())))
+            .replace("{day}", &date.day().to_string())
+            .replace("{year}", &date.year().to_string())
+    }
+
+    /// Replace `{{ t.key }}` placeholders and `{{ lang }}` in a template
+    pub fn localize(&self, template: &str) -> String {
+        self.fill(template, true)
+    }
+
+    /// Like `localize`, for plain-text templates such as emails where nothing is escaped
+    pub fn localize_text(&self, template: &str) -> String {
+        self.fill(template, false)
+    }
+
+    fn fill(&self, template: &str, escape: bool) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{ t.") {
+            let Some(len) = rest[start..].find(" }}") else {
+                break;
+            };
+            let key = &rest[start + "{{ t.".len()..start + len];
+            output.push_str(&rest[..start]);
+            let message = self.t(key);
+            if escape {
+                output.push_str(&crate::utils::escape_html(&message));
+            } else {
+                output.push_str(&message);
+            }
+            rest = &rest[start + len + " }}".len()..];
+        }
+        output.push_str(rest);
+
+        output.replace("{{ lang }}", self.0)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Locale::from_headers(&parts.headers))
+    }
+}
+
+/// Remember the reader's language choice in a cookie and send them back
+pub async fn set_locale(Path(locale): Path<String>, headers: HeaderMap) -> Response {
+    // Only ever redirect to a path on this site, whatever the Referer says
+    let back = headers
+        .get(header::REFERER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|referer| match referer.split_once("://") {
+            Some((_, rest)) => rest.find('/').map(|i| &rest[i..]),
+            None => Some(referer),
+        })
+        .filter(|path| path.starts_with('/') && !path.starts_with("//"))
+        .unwrap_or("/")
+        .to_string();
+    let mut response = Redirect::to(&back).into_response();
+
+    if let Some(locale) = normalize(&locale) {
+        let cookie = format!(
+            "{}={}; Path=/; SameSite=Lax; Max-Age={}",
+            LOCALE_COOKIE,
+            locale,
+            365 * 24 * 60 * 60
+        );
+        if let Ok(value) = cookie.parse() {
+            response.headers_mut().insert(header::SET_COOKIE, value);
+        }
+    }
+
+    response
+}
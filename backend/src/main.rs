@@ -1,36 +1,46 @@
-#![allow(warnings)]
-
-use crate::auth::{
-    auth_middleware, 
-    jwt::validate_token, 
-    oauth::{callback_handler, login_handler, logout_handler, OAuthConfig},
-};
-use axum::http::HeaderName;
+use crate::auth::oauth::{callback_handler, login_handler, logout_handler, OAuthConfig};
+use crate::i18n::Locale;
 use axum::{
-    extract::{Form, Path, Query, State},
+    extract::{DefaultBodyLimit, Form, Path, Query},
     http::{header, HeaderMap, Method, StatusCode},
     middleware,
     response::{Html, Json, Response},
     routing::{delete, get, post, put},
-    Router,
+    Extension, Router,
 };
 use serde_json::json;
 use std::net::SocketAddr;
-use std::{env, fs, path::Path as StdPath, sync::Arc, time::Duration};
+use std::{env, path::Path as StdPath, sync::Arc, time::Duration};
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 
+mod activitypub;
+mod analytics;
+mod archive;
 mod auth;
+mod comments;
+mod export;
+mod feeds;
+mod fetcher;
+mod i18n;
 mod markdown;
+mod media;
+mod newsletter;
+mod notifications;
+mod preview;
+mod reactions;
+mod seo;
+mod spam;
+#[cfg(test)]
+mod testing;
 mod utils;
+mod webmention;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
     tracing_subscriber::fmt::init();
 
-    println!("🚀 Starting blog backend server with Axum and Keycloak auth...");
-
     // Configure OAuth with PKCE (no client secret needed for public clients)
     let oauth_config = OAuthConfig::new(
         "blog-client".to_string(),
@@ -49,6 +59,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .parse::<u16>()
         .unwrap_or(8000);
 
+    // Static export runs the public pages through the router instead of serving them
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("export") {
+        let out_dir = args.get(2).map(String::as_str).unwrap_or("dist");
+        export::run(app(oauth_config), StdPath::new(out_dir)).await?;
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("media-gc") {
+        media::library::collect_garbage(args.iter().any(|arg| arg == "--dry-run"))?;
+        return Ok(());
+    }
+
+    println!("🚀 Starting blog backend server with Axum and Keycloak auth...");
+
+    let app = app(oauth_config);
+
+    // Newsletter emails are sent in the background at a throttled rate
+    newsletter::queue::spawn_worker();
+    preview::spawn_pruner();
+    analytics::spawn_flusher();
+
+    // Start server
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    println!("📡 Server running on http://0.0.0.0:{}", port);
+    axum::serve(
+        tokio::net::TcpListener::bind(addr).await?,
+        app.into_make_service(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// All routes of the application, shared by the server and the static export
+fn app(oauth_config: Arc<OAuthConfig>) -> Router {
     // Create CORS layer with proper configuration for credentials and headers
     let cors = CorsLayer::new()
         .allow_methods([
@@ -83,7 +128,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .max_age(Duration::from_secs(3600));
 
     // Build our application with routes
-    let app = Router::new()
+    Router::new()
         // Auth routes
         .route("/auth/login", get(login_handler))
         .route("/auth/callback", get(callback_handler))
@@ -96,13 +141,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/posts", get(list_posts))
         .route("/posts/{slug}", get(get_post))
         .route("/preview", post(preview_markdown))
+        .route("/preview/live/{draft_id}", get(preview::stream_draft))
+        .route("/preview/watch/{draft_id}", get(preview::watch_draft))
         // Admin routes
         .route("/admin/new", get(serve_new_post))
         .route("/admin/edit/{slug}", get(serve_edit_post))
+        .route("/admin/translate/{slug}", get(serve_translate_post))
+        .route("/admin/media", get(media::serve_library_page))
+        .route("/admin/comments", get(comments::serve_moderation_page))
+        .route("/admin/analytics", get(analytics::serve_dashboard_page))
+        .route("/admin/notifications", get(notifications::serve_notifications_page))
         // Frontend routes
         .route("/", get(serve_index))
         .route("/static/{file}", get(serve_static))
+        .route("/media/{file}", get(media::serve))
         .route("/favicon.ico", get(serve_favicon))
+        .route("/lang/{locale}", get(i18n::set_locale))
+        // Syndication feeds
+        .route("/feed.xml", get(feeds::rss_feed))
+        .route("/atom.xml", get(feeds::atom_feed))
+        .route("/feed.json", get(feeds::json_feed::json_feed))
+        // Crawler support
+        .route("/sitemap.xml", get(seo::sitemap::sitemap_xml))
+        .route("/sitemaps/{file}", get(seo::sitemap::sitemap_chunk))
+        .route("/robots.txt", get(seo::robots::robots_txt))
+        // Federation
+        .route("/.well-known/webfinger", get(activitypub::webfinger))
+        .route("/ap/actor", get(activitypub::actor))
+        .route("/ap/inbox", post(activitypub::inbox))
+        .route("/ap/outbox", get(activitypub::outbox))
+        .route("/ap/followers", get(activitypub::followers))
+        .route("/ap/posts/{slug}", get(activitypub::post_object))
+        .route("/webmention", post(webmention::receive))
+        .route(
+            "/posts/{slug}/comments",
+            get(comments::fragment).post(comments::submit),
+        )
+        .route("/posts/{slug}/reactions", get(reactions::fragment))
+        .route("/posts/{slug}/reactions/{kind}", post(reactions::toggle))
+        .route("/newsletter/subscribe", post(newsletter::subscribe))
+        .route("/newsletter/confirm/{token}", get(newsletter::confirm))
+        .route(
+            "/newsletter/unsubscribe/{token}",
+            get(newsletter::unsubscribe_page).post(newsletter::unsubscribe),
+        )
+        .route("/archive", get(archive::archive_index))
+        .route("/archive/html", get(archive::archive_html))
+        .route("/archive/widget", get(archive::archive_widget))
+        .route("/archive/{year}", get(archive::archive_year))
+        .route("/archive/{year}/{month}", get(archive::archive_month))
+        .route("/tags/{tag}", get(serve_tag_page))
+        .route("/tags/{tag}/feed.xml", get(feeds::tag_rss_feed))
+        .route("/tags/{tag}/atom.xml", get(feeds::tag_atom_feed))
+        .route("/authors/{author}/feed.xml", get(feeds::author_rss_feed))
+        .route("/authors/{author}/atom.xml", get(feeds::author_atom_feed))
         .route("/posts/html", get(serve_posts_html))
         .nest(
             "/admin",
@@ -110,22 +202,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .route("/new", post(create_post))
                 .route("/edit/{slug}", put(edit_post))
                 .route("/delete/{slug}", delete(delete_post))
+                .route("/preview/{draft_id}", post(preview::update_draft))
+                .route(
+                    "/media",
+                    post(media::upload).layer(DefaultBodyLimit::max(media::upload_body_limit())),
+                )
+                .route("/media/library", get(media::library))
+                .route("/media/library/html", get(media::library_html))
+                .route("/media/{file}", delete(media::delete))
+                .route("/webmentions", get(webmention::list))
+                .route("/webmentions/{id}/approve", post(webmention::approve))
+                .route("/webmentions/{id}/reject", post(webmention::reject))
+                .route("/webmentions/{id}/spam", post(webmention::mark_spam))
+                .route("/webmentions/{id}", delete(webmention::delete))
+                .route("/comments/queue", get(comments::list))
+                .route("/comments/queue/html", get(comments::list_html))
+                .route("/comments/{id}/approve", post(comments::approve))
+                .route("/comments/{id}/reject", post(comments::reject))
+                .route("/comments/{id}/spam", post(comments::mark_spam))
+                .route("/comments/{id}", delete(comments::delete))
+                .route("/analytics/summary", get(analytics::summary))
+                .route("/analytics/summary/html", get(analytics::summary_html))
+                .route("/notifications/list", get(notifications::list))
+                .route("/notifications/list/html", get(notifications::list_html))
+                .route("/notifications/badge", get(notifications::badge))
+                .route("/notifications/stream", get(notifications::stream))
+                .route("/notifications/read", post(notifications::mark_all_read))
+                .route("/notifications/{id}/read", post(notifications::mark_one_read))
                 .layer(middleware::from_fn(auth::auth_middleware)),
         )
         .layer(cors)
         .layer(TraceLayer::new_for_http())
-        .with_state(oauth_config);
-
-    // Start server
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    println!("📡 Server running on http://{}:{}", "0.0.0.0", port);
-    axum::serve(
-        tokio::net::TcpListener::bind(addr).await?,
-        app.into_make_service(),
-    )
-    .await?;
-
-    Ok(())
+        .with_state(oauth_config)
 }
 
 async fn health_check() -> Json<serde_json::Value> {
@@ -189,19 +297,28 @@ async fn auth_status(headers: HeaderMap) -> Json<serde_json::Value> {
     }
 }
 
-async fn list_posts() -> Json<serde_json::Value> {
+#[derive(serde::Deserialize)]
+struct ListQuery {
+    lang: Option<String>,
+    tag: Option<String>,
+}
+
+async fn list_posts(Query(query): Query<ListQuery>) -> Json<serde_json::Value> {
     match std::fs::read_to_string("posts.json") {
         Ok(content) => match serde_json::from_str::<Vec<crate::markdown::Post>>(&content) {
             Ok(posts) => {
                 let post_summaries: Vec<serde_json::Value> = posts
                     .iter()
+                    .filter(|post| query.lang.as_ref().is_none_or(|lang| &post.lang == lang))
                     .map(|post| {
                         json!({
                             "slug": post.slug,
                             "title": post.title,
                             "author": post.author,
                             "created_at": post.created_at,
-                            "updated_at": post.updated_at
+                            "updated_at": post.updated_at,
+                            "lang": post.lang,
+                            "translation_of": post.translation_of
                         })
                     })
                     .collect();
@@ -223,75 +340,181 @@ async fn list_posts() -> Json<serde_json::Value> {
     }
 }
 
-async fn get_post(Path(slug): Path<String>) -> Result<Html<String>, StatusCode> {
-
+async fn get_post(
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    let not_found = format!(
+        "<h1>{}</h1><p>{}</p>",
+        locale.t("post.not_found"),
+        locale.t("post.not_found_message")
+    );
 
     // Try to get post data first
     match crate::markdown::reader::read_post(&slug) {
         Ok(post) => {
+            crate::analytics::record(&post.slug, &headers);
+
             // Read the post template
             match std::fs::read_to_string("../frontend/templates/post.html") {
-                Ok(mut template) => {
+                Ok(template) => {
                     // Simple template replacement
+                    let translations =
+                        crate::markdown::reader::read_translations(&post).unwrap_or_default();
+                    let (alternates, switcher) = translation_links(&post, &translations, &locale);
+
+                    let mut template = locale.localize(&template);
+                    template = template.replace("{{ alternates | safe }}", &alternates);
+                    template = template.replace(
+                        "{{ meta | safe }}",
+                        &crate::seo::meta::PageMeta::for_post(&post).render(),
+                    );
+                    template = template.replace(
+                        "{{ structured_data | safe }}",
+                        &crate::seo::structured_data::script_tag(
+                            &crate::seo::structured_data::BlogPosting::for_post(&post),
+                        ),
+                    );
+                    template = template.replace("{{ translations | safe }}", &switcher);
+                    let cover = post.cover.as_ref().map_or(String::new(), |cover| {
+                        crate::media::cover_img(cover, "post-cover", "(max-width: 1200px) 100vw, 1200px", true)
+                    });
+                    template = template.replace("{{ cover | safe }}", &cover);
+                    template = template.replace("{{ post_lang }}", &post.lang);
                     template = template.replace("{{ title }}", &post.title);
                     template = template.replace("{{ author }}", &post.author);
                     template = template.replace(
                         "{{ created_at }}",
-                        &post.created_at.format("%B %d, %Y").to_string(),
+                        &locale.format_date(&post.created_at),
                     );
                     template = template.replace(
                         "{{ updated_at }}",
-                        &post.updated_at.format("%B %d, %Y").to_string(),
+                        &locale.format_date(&post.updated_at),
                     );
 
                     // Get the rendered content
-                    match crate::markdown::reader::read_and_render_markdown(&slug) {
-                        Ok(html_content) => {
-                            template = template.replace("{{ content | safe }}", &html_content);
+                    let content = crate::markdown::cache::render_post(&slug)
+                        .unwrap_or_else(|_| format!("<p>{}</p>", locale.t("post.content_error")));
+                    template = template.replace("{{ content | safe }}", &content);
 
-                            Ok(Html(template))
-                        }
-                        Err(_) => {
-                            template = template
-                                .replace("{{ content | safe }}", "<p>Error loading content.</p>");
-                            Ok(Html(template))
-                        }
-                    }
+                    // Sections written by readers go in last, so placeholders typed into them stay text
+                    template = template.replace(
+                        "{{ webmentions | safe }}",
+                        &crate::webmention::render(&post.slug, &locale),
+                    );
+                    template = template.replace(
+                        "{{ reactions | safe }}",
+                        &crate::reactions::section(&post.slug, &locale),
+                    );
+                    template = template.replace(
+                        "{{ comments | safe }}",
+                        &crate::comments::section(&post, &locale),
+                    );
+
+                    Ok(Html(template))
                 }
                 Err(_) => {
                     // Fallback to simple HTML if template not found
-                    match crate::markdown::reader::read_and_render_markdown(&slug) {
+                    match crate::markdown::cache::render_post(&slug) {
                         Ok(html_content) => {
                             let simple_html = format!(
-                                "<!DOCTYPE html><html><head><title>{}</title></head><body><h1>{}</h1><div>{}</div></body></html>",
-                                post.title, post.title, html_content
+                                "<!DOCTYPE html><html lang=\"{}\"><head><title>{}</title></head><body><h1>{}</h1><div>{}</div></body></html>",
+                                locale.code(), post.title, post.title, html_content
                             );
                             Ok(Html(simple_html))
                         }
-                        Err(_) => Ok(Html(
-                            "<h1>Post not found</h1><p>The requested post could not be found.</p>"
-                                .to_string(),
-                        )),
+                        Err(_) => Ok(Html(not_found)),
                     }
                 }
             }
         }
-        Err(_) => {
+        Err(_) => Ok(Html(not_found)),
+    }
+}
 
-            Ok(Html(
-                "<h1>Post not found</h1><p>The requested post could not be found.</p>".to_string(),
-            ))
+/// `hreflang` alternate links for the page head and a language switcher for the post
+fn translation_links(
+    post: &crate::markdown::Post,
+    translations: &[crate::markdown::Post],
+    locale: &Locale,
+) -> (String, String) {
+    if translations.len() < 2 {
+        return (String::new(), String::new());
+    }
+
+    let base_url = crate::utils::public_base_url();
+    let mut alternates = String::new();
+    let mut links = Vec::new();
+
+    for translation in translations {
+        let url = format!("{}/posts/{}", base_url, translation.slug);
+        alternates.push_str(&format!(
+            "<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">\n",
+            translation.lang, url
+        ));
+        if translation.slug != post.slug {
+            links.push(format!(
+                "<a href=\"/posts/{}\" hreflang=\"{}\" lang=\"{}\">{}</a>",
+                translation.slug,
+                translation.lang,
+                translation.lang,
+                locale.t(&format!("language.{}", translation.lang))
+            ));
         }
     }
+    // The source post is listed first and doubles as the fallback
+    alternates.push_str(&format!(
+        "<link rel=\"alternate\" hreflang=\"x-default\" href=\"{}/posts/{}\">\n",
+        base_url, translations[0].slug
+    ));
+
+    let switcher = format!(
+        "<nav class=\"post-translations\">{} {}</nav>",
+        locale.t("post.translations"),
+        links.join(" ")
+    );
+
+    (alternates, switcher)
 }
 
 #[derive(serde::Deserialize)]
 struct PreviewRequest {
     content: String,
+    #[serde(default)]
+    title: Option<String>,
 }
 
 async fn preview_markdown(Json(payload): Json<PreviewRequest>) -> Html<String> {
-    let html_content = crate::markdown::reader::markdown_to_html(&payload.content);
+    let rendered = crate::markdown::reader::render_markdown(&payload.content);
+
+    let options = crate::markdown::lint::LintOptions::from_env();
+    let mut report = match &payload.title {
+        Some(title) => crate::markdown::lint::lint_post(title, &payload.content, &options),
+        None => crate::markdown::lint::lint_content(&payload.content, &options),
+    };
+    report.warnings.extend(rendered.warnings);
+
+    // Surface validation problems and unknown shortcodes to the author above the preview
+    let mut html_content = String::new();
+    for (class, messages) in [("error", &report.errors), ("info", &report.warnings)] {
+        if messages.is_empty() {
+            continue;
+        }
+        html_content.push_str(&format!(
+            "<div class=\"message {} preview-warnings\"><ul>",
+            class
+        ));
+        for message in messages {
+            html_content.push_str(&format!(
+                "<li>{}</li>",
+                crate::utils::escape_html(message)
+            ));
+        }
+        html_content.push_str("</ul></div>");
+    }
+    html_content.push_str(&rendered.html);
+
     Html(html_content)
 }
 
@@ -299,6 +522,29 @@ async fn preview_markdown(Json(payload): Json<PreviewRequest>) -> Html<String> {
 struct CreatePostRequest {
     title: String,
     content: String,
+    #[serde(default)]
+    lang: Option<String>,
+    #[serde(default)]
+    translation_of: Option<String>,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    seo_title: String,
+    #[serde(default)]
+    seo_description: String,
+    #[serde(default)]
+    seo_image: String,
+    #[serde(default)]
+    cover_image: String,
+    #[serde(default)]
+    cover_alt: String,
+    #[serde(default)]
+    cover_focal_x: String,
+    #[serde(default)]
+    cover_focal_y: String,
+    /// Checkbox, present when comments are closed
+    #[serde(default)]
+    comments_closed: Option<String>,
 }
 
 #[derive(serde::Serialize)]
@@ -306,23 +552,109 @@ struct AdminResponse {
     success: bool,
     message: String,
     slug: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+impl AdminResponse {
+    /// Response for a post whose cover is not an uploaded image
+    fn invalid_cover(warnings: Vec<String>) -> Self {
+        Self {
+            success: false,
+            message: "Post failed validation".to_string(),
+            slug: None,
+            errors: vec!["The cover image must be an image from the media library".to_string()],
+            warnings,
+        }
+    }
+
+    /// Response for a post rejected by validation
+    fn invalid(report: crate::markdown::lint::LintReport) -> Self {
+        Self {
+            success: false,
+            message: "Post failed validation".to_string(),
+            slug: None,
+            errors: report.errors,
+            warnings: report.warnings,
+        }
+    }
 }
 
 async fn create_post(
+    Extension(claims): Extension<auth::jwt::Claims>,
     Form(payload): Form<CreatePostRequest>,
 ) -> Result<Json<AdminResponse>, StatusCode> {
-    // Authentication is handled by middleware; claims should be available in request extensions
-    // For now, we'll use a placeholder author - in a real implementation,
-    // we'd extract the claims from the middleware
+    let report = crate::markdown::lint::lint_post(
+        &payload.title,
+        &payload.content,
+        &crate::markdown::lint::LintOptions::from_env(),
+    );
+    if !report.is_ok() {
+        return Ok(Json(AdminResponse::invalid(report)));
+    }
+
+    let cover = crate::markdown::CoverImage::from_form(
+        &payload.cover_image,
+        &payload.cover_alt,
+        &payload.cover_focal_x,
+        &payload.cover_focal_y,
+    );
+    if cover.as_ref().is_some_and(|cover| !crate::media::is_uploaded_image(&cover.url)) {
+        return Ok(Json(AdminResponse::invalid_cover(report.warnings)));
+    }
+
+    let lang = payload
+        .lang
+        .as_deref()
+        .and_then(crate::i18n::normalize)
+        .unwrap_or_else(crate::i18n::default_locale)
+        .to_string();
+
+    // Translations always point at the source post of their group
+    let translation_of = match payload.translation_of.as_deref().filter(|s| !s.is_empty()) {
+        Some(source_slug) => {
+            let source = match crate::markdown::reader::read_post(source_slug) {
+                Ok(source) => source,
+                Err(_) => return Err(StatusCode::NOT_FOUND),
+            };
+            let translations =
+                crate::markdown::reader::read_translations(&source).unwrap_or_default();
+            if translations.iter().any(|t| t.lang == lang) {
+                return Ok(Json(AdminResponse {
+                    success: false,
+                    message: format!("A {} translation of this post already exists", lang),
+                    slug: None,
+                    errors: Vec::new(),
+                    warnings: report.warnings,
+                }));
+            }
+            Some(source.translation_group().to_string())
+        }
+        None => None,
+    };
+
     let slug = crate::utils::generate_unique_slug(&payload.title);
 
     let post = crate::markdown::Post {
         slug: slug.clone(),
         title: payload.title,
-        author: "placeholder-author".to_string(),
+        author: claims.display_name(),
+        author_sub: Some(claims.sub),
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
         content: payload.content,
+        lang,
+        translation_of,
+        tags: crate::utils::parse_tags(&payload.tags),
+        seo: crate::markdown::SeoOverrides::from_form(
+            &payload.seo_title,
+            &payload.seo_description,
+            &payload.seo_image,
+        ),
+        cover,
+        comments_closed: payload.comments_closed.is_some(),
     };
 
     // Save the post
@@ -333,10 +665,12 @@ async fn create_post(
                 success: true,
                 message: "Post created successfully".to_string(),
                 slug: Some(slug),
+                errors: Vec::new(),
+                warnings: report.warnings,
             }))
         }
         Err(e) => {
-
+            tracing::error!("Failed to create post: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
@@ -346,12 +680,50 @@ async fn create_post(
 struct UpdatePostRequest {
     title: String,
     content: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    seo_title: String,
+    #[serde(default)]
+    seo_description: String,
+    #[serde(default)]
+    seo_image: String,
+    #[serde(default)]
+    cover_image: String,
+    #[serde(default)]
+    cover_alt: String,
+    #[serde(default)]
+    cover_focal_x: String,
+    #[serde(default)]
+    cover_focal_y: String,
+    /// Checkbox, present when comments are closed
+    #[serde(default)]
+    comments_closed: Option<String>,
 }
 
 async fn edit_post(
     Path(slug): Path<String>,
     Form(payload): Form<UpdatePostRequest>,
 ) -> Result<Json<AdminResponse>, StatusCode> {
+    let report = crate::markdown::lint::lint_post(
+        &payload.title,
+        &payload.content,
+        &crate::markdown::lint::LintOptions::from_env(),
+    );
+    if !report.is_ok() {
+        return Ok(Json(AdminResponse::invalid(report)));
+    }
+
+    let cover = crate::markdown::CoverImage::from_form(
+        &payload.cover_image,
+        &payload.cover_alt,
+        &payload.cover_focal_x,
+        &payload.cover_focal_y,
+    );
+    if cover.as_ref().is_some_and(|cover| !crate::media::is_uploaded_image(&cover.url)) {
+        return Ok(Json(AdminResponse::invalid_cover(report.warnings)));
+    }
+
     // Load existing post to preserve author and created_at
     let existing_post = match crate::markdown::reader::read_post(&slug) {
         Ok(p) => p,
@@ -362,9 +734,20 @@ async fn edit_post(
         slug: slug.clone(),
         title: payload.title,
         author: existing_post.author,
+        author_sub: existing_post.author_sub,
         created_at: existing_post.created_at,
         updated_at: chrono::Utc::now(),
         content: payload.content,
+        lang: existing_post.lang,
+        translation_of: existing_post.translation_of,
+        tags: crate::utils::parse_tags(&payload.tags),
+        seo: crate::markdown::SeoOverrides::from_form(
+            &payload.seo_title,
+            &payload.seo_description,
+            &payload.seo_image,
+        ),
+        cover,
+        comments_closed: payload.comments_closed.is_some(),
     };
 
     // Update the post
@@ -375,10 +758,12 @@ async fn edit_post(
                 success: true,
                 message: "Post updated successfully".to_string(),
                 slug: Some(slug),
+                errors: Vec::new(),
+                warnings: report.warnings,
             }))
         }
         Err(e) => {
-
+            tracing::error!("Failed to update post: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
@@ -394,41 +779,165 @@ async fn delete_post(Path(slug): Path<String>) -> Result<Json<AdminResponse>, St
                 success: true,
                 message: "Post deleted successfully".to_string(),
                 slug: Some(slug),
+                errors: Vec::new(),
+                warnings: Vec::new(),
             }))
         }
         Err(e) => {
-
+            tracing::error!("Failed to delete post: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
 // Template serving functions
-async fn serve_index() -> Html<String> {
+async fn serve_index(locale: Locale) -> Html<String> {
     match std::fs::read_to_string("../frontend/templates/index.html") {
-        Ok(content) => Html(content),
+        Ok(content) => {
+            let mut posts = crate::markdown::reader::read_posts().unwrap_or_default();
+            posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+            let listed = select_language(posts.clone(), None, &locale);
+            let (blog, list) = crate::seo::structured_data::for_index(&posts);
+            let structured_data = format!(
+                "{}\n    {}",
+                crate::seo::structured_data::script_tag(&blog),
+                crate::seo::structured_data::script_tag(&list)
+            );
+
+            Html(locale
+                .localize(&content)
+                .replace("{{ structured_data | safe }}", &structured_data)
+                .replace("{{ posts | safe }}", &archive::render_groups(&listed, &locale))
+                .replace("{{ archive_widget | safe }}", &archive::render_widget(&listed, &locale)))
+        }
         Err(_) => Html("<h1>Error</h1><p>Could not load index template.</p>".to_string()),
     }
 }
 
-async fn serve_new_post() -> Html<String> {
+async fn serve_new_post(locale: Locale) -> Html<String> {
+    render_new_post(&locale, "", "", &[], locale.code(), None, None)
+}
+
+#[derive(serde::Deserialize)]
+struct TranslateQuery {
+    lang: Option<String>,
+}
+
+/// New post form prefilled with the source post, to be saved as its translation
+async fn serve_translate_post(
+    Path(slug): Path<String>,
+    Query(query): Query<TranslateQuery>,
+    locale: Locale,
+) -> Html<String> {
+    match crate::markdown::reader::read_post(&slug) {
+        Ok(source) => {
+            let lang = query
+                .lang
+                .as_deref()
+                .and_then(crate::i18n::normalize)
+                .or_else(|| crate::i18n::supported_locales().find(|l| *l != source.lang))
+                .unwrap_or(crate::i18n::DEFAULT_LOCALE);
+            render_new_post(
+                &locale,
+                &source.title,
+                &source.content,
+                &source.tags,
+                lang,
+                Some(&source.slug),
+                source.cover.as_ref(),
+            )
+        }
+        Err(_) => Html("<h1>Error</h1><p>Post not found.</p>".to_string()),
+    }
+}
+
+fn render_new_post(
+    locale: &Locale,
+    title: &str,
+    content: &str,
+    tags: &[String],
+    lang: &str,
+    translation_of: Option<&str>,
+    cover: Option<&crate::markdown::CoverImage>,
+) -> Html<String> {
     match std::fs::read_to_string("../frontend/templates/admin/new.html") {
-        Ok(content) => Html(content),
+        Ok(template) => {
+            let lang_options: String = crate::i18n::supported_locales()
+                .map(|code| {
+                    format!(
+                        "<option value=\"{}\"{}>{}</option>",
+                        code,
+                        if code == lang { " selected" } else { "" },
+                        locale.t(&format!("language.{}", code))
+                    )
+                })
+                .collect();
+
+            let mut template = locale.localize(&template);
+            template = template.replace("{{ lang_options | safe }}", &lang_options);
+            template = template.replace(
+                "{{ translation_of }}",
+                &crate::utils::escape_html(translation_of.unwrap_or_default()),
+            );
+            template = template.replace("{{ title }}", &crate::utils::escape_html(title));
+            template = template.replace("{{ tags }}", &crate::utils::escape_html(&tags.join(", ")));
+            for field in ["{{ seo_title }}", "{{ seo_description }}", "{{ seo_image }}"] {
+                template = template.replace(field, "");
+            }
+            template = fill_cover_fields(template, cover);
+            template = template.replace("{{ comments_closed }}", "");
+            template = template.replace("{{ content }}", &crate::utils::escape_html(content));
+            Html(template)
+        }
         Err(_) => Html("<h1>Error</h1><p>Could not load new post template.</p>".to_string()),
     }
 }
 
-async fn serve_edit_post(Path(slug): Path<String>) -> Html<String> {
+/// Fill the cover image fields of the editor, the focal point is edited in percent
+fn fill_cover_fields(template: String, cover: Option<&crate::markdown::CoverImage>) -> String {
+    let percent = |fraction: f32| format!("{:.0}", fraction * 100.0);
+    let (url, alt, focal_x, focal_y) = match cover {
+        Some(cover) => (
+            cover.url.clone(),
+            cover.alt.clone(),
+            percent(cover.focal_x),
+            percent(cover.focal_y),
+        ),
+        None => (String::new(), String::new(), "50".to_string(), "50".to_string()),
+    };
+    template
+        .replace("{{ cover_image }}", &crate::utils::escape_html(&url))
+        .replace("{{ cover_alt }}", &crate::utils::escape_html(&alt))
+        .replace("{{ cover_focal_x }}", &focal_x)
+        .replace("{{ cover_focal_y }}", &focal_y)
+}
+
+async fn serve_edit_post(Path(slug): Path<String>, locale: Locale) -> Html<String> {
     // First try to get the existing post data
     match crate::markdown::reader::read_post(&slug) {
         Ok(post) => {
             // Read the template
             match std::fs::read_to_string("../frontend/templates/admin/edit.html") {
-                Ok(mut template) => {
+                Ok(template) => {
                     // Simple template replacement (in a real app, use a proper templating engine)
+                    let mut template = locale.localize(&template);
                     template = template.replace("{{ slug }}", &slug);
-                    template = template.replace("{{ title }}", &post.title);
-                    template = template.replace("{{ content }}", &post.content);
+                    let escape = crate::utils::escape_html;
+                    template = template.replace("{{ title }}", &escape(&post.title));
+                    template = template.replace("{{ tags }}", &escape(&post.tags.join(", ")));
+                    let field = |value: &Option<String>| escape(value.as_deref().unwrap_or_default());
+                    template = template.replace("{{ seo_title }}", &field(&post.seo.title));
+                    template = template.replace(
+                        "{{ seo_description }}",
+                        &field(&post.seo.description),
+                    );
+                    template = template.replace("{{ seo_image }}", &field(&post.seo.image));
+                    template = fill_cover_fields(template, post.cover.as_ref());
+                    template = template.replace(
+                        "{{ comments_closed }}",
+                        if post.comments_closed { "checked" } else { "" },
+                    );
+                    template = template.replace("{{ content }}", &escape(&post.content));
                     Html(template)
                 }
                 Err(_) => Html("<h1>Error</h1><p>Could not load edit template.</p>".to_string()),
@@ -473,7 +982,7 @@ async fn serve_static(Path(file): Path<String>) -> Result<Response, StatusCode>
 
 
 // Serve posts as HTML for HTMX
-async fn serve_posts_html() -> Html<String> {
+async fn serve_posts_html(locale: Locale, Query(query): Query<ListQuery>) -> Html<String> {
     // Use absolute path to posts.json
     let current_dir = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
     let posts_path = current_dir.join("posts.json");
@@ -481,42 +990,111 @@ async fn serve_posts_html() -> Html<String> {
     match std::fs::read_to_string(posts_path) {
         Ok(content) => match serde_json::from_str::<Vec<crate::markdown::Post>>(&content) {
             Ok(posts) => {
-                let mut html = String::new();
+                let mut posts = select_language(posts, query.lang.as_deref(), &locale);
+                if let Some(tag) = &query.tag {
+                    posts.retain(|post| post.has_tag(tag));
+                }
+                posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
 
-                if posts.is_empty() {
-                    html.push_str("<p class='no-posts'>No posts available yet.</p>");
-                } else {
-                    for post in posts {
-                        let date = post.created_at.format("%B %d, %Y").to_string();
+                Html(archive::render_groups(&posts, &locale))
+            }
+            Err(_) => Html(format!("<p class='no-posts'>{}</p>", locale.t("posts.error"))),
+        },
+        Err(_) => Html(format!("<p class='no-posts'>{}</p>", locale.t("posts.error"))),
+    }
+}
 
-                        html.push_str(&format!(
-                            r#"
-<article class="post-card">
+/// Post cards shown in listings
+fn render_post_cards(posts: &[crate::markdown::Post], locale: &Locale) -> String {
+    if posts.is_empty() {
+        return format!("<p class='no-posts'>{}</p>", locale.t("posts.none"));
+    }
+
+    let reactions = crate::reactions::store::all_counts();
+    let mut html = String::new();
+    for post in posts {
+        let date = locale.format_date(&post.created_at);
+
+        html.push_str(&format!(
+            r#"
+<article class="post-card">{}
     <div class="post-header">
         <h3 class="post-title">
             <a href="/posts/{}" class="post-link">{}</a>
         </h3>
         <div class="post-meta">
-            <span class="post-author">By {}</span>
+            <span class="post-author">{} {}</span>
             <span class="post-date">{}</span>
-        </div>
+        </div>{}
     </div>
     <div class="post-actions">
-        <a href="/posts/{}" class="btn btn-primary">Read More</a>
+        <a href="/posts/{}" class="btn btn-primary">{}</a>
     </div>
 </article>
-                                "#,
-                            post.slug, post.title, post.author, date, post.slug
-                        ));
-                    }
-                }
+                "#,
+            post.cover.as_ref().map_or(String::new(), |cover| format!(
+                r#"
+    <a href="/posts/{}" class="post-card-cover" tabindex="-1">{}</a>"#,
+                post.slug,
+                crate::media::cover_img(cover, "post-card-image", "(max-width: 768px) 100vw, 400px", false)
+            )),
+            post.slug,
+            post.title,
+            locale.t("posts.by"),
+            post.author,
+            date,
+            crate::reactions::summary(reactions.get(&post.slug), locale),
+            post.slug,
+            locale.t("posts.read_more")
+        ));
+    }
+    html
+}
 
-                Html(html)
-            }
-            Err(_) => Html("<p class='no-posts'>Error loading posts.</p>".to_string()),
-        },
-        Err(_) => Html("<p class='no-posts'>Error loading posts.</p>".to_string()),
+/// Listing of the posts carrying a tag
+async fn serve_tag_page(Path(tag): Path<String>, locale: Locale) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/tag.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let tag = tag.to_lowercase();
+    let posts = crate::feeds::scoped_posts(&crate::feeds::FeedScope::Tag(tag.clone()))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if posts.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let posts = select_language(posts, None, &locale);
+
+    Ok(Html(
+        locale
+            .localize(&template)
+            .replace("{{ tag_path }}", &urlencoding::encode(&tag))
+            .replace("{{ tag }}", &crate::utils::escape_html(&tag))
+            .replace("{{ posts | safe }}", &render_post_cards(&posts, &locale)),
+    ))
+}
+
+/// Keep posts in the requested language, or else one version of each post preferring the reader's
+fn select_language(
+    posts: Vec<crate::markdown::Post>,
+    lang: Option<&str>,
+    locale: &Locale,
+) -> Vec<crate::markdown::Post> {
+    if let Some(lang) = lang {
+        return posts.into_iter().filter(|post| post.lang == lang).collect();
     }
+
+    let preferred: std::collections::HashSet<String> = posts
+        .iter()
+        .filter(|post| post.lang == locale.code())
+        .map(|post| post.translation_group().to_string())
+        .collect();
+
+    posts
+        .into_iter()
+        .filter(|post| {
+            post.lang == locale.code()
+                || (post.translation_of.is_none() && !preferred.contains(&post.slug))
+        })
+        .collect()
 }
 
 // Serve favicon
@@ -0,0 +1,161 @@
+use crate::markdown::reader;
+use anyhow::Result;
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Fingerprint of everything besides the markdown that affects rendered output.
+/// Bump this whenever the render pipeline changes so stale entries are not served.
+pub const RENDER_OPTIONS: &str = "markdown-rs/1.0 shortcodes/1 media-srcset/1";
+/// Renders kept in memory; the oldest are dropped first, the disk layer still has them
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Default)]
+struct RenderCache {
+    // content hash -> rendered HTML
+    entries: DashMap<String, String>,
+    // post slug -> content hash of its last render
+    slugs: DashMap<String, String>,
+    // content hashes in `entries`, oldest first
+    order: Mutex<VecDeque<String>>,
+}
+
+fn cache() -> &'static RenderCache {
+    static CACHE: OnceLock<RenderCache> = OnceLock::new();
+    CACHE.get_or_init(RenderCache::default)
+}
+
+/// Optional on-disk layer, enabled by setting BLOG_RENDER_CACHE_DIR
+fn disk_dir() -> Option<PathBuf> {
+    std::env::var("BLOG_RENDER_CACHE_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Cache key for a piece of markdown under the current render options
+pub fn cache_key(markdown: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(RENDER_OPTIONS.as_bytes());
+    hasher.update([0]);
+    hasher.update(markdown.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Keep a render in memory, dropping the oldest ones past MAX_ENTRIES
+fn remember(key: &str, html: &str) {
+    let mut order = cache().order.lock().unwrap_or_else(PoisonError::into_inner);
+    if cache().entries.insert(key.to_string(), html.to_string()).is_none() {
+        order.push_back(key.to_string());
+    }
+    while order.len() > MAX_ENTRIES {
+        if let Some(oldest) = order.pop_front() {
+            cache().entries.remove(&oldest);
+        }
+    }
+}
+
+fn lookup(key: &str) -> Option<String> {
+    if let Some(html) = cache().entries.get(key) {
+        return Some(html.clone());
+    }
+
+    let html = fs::read_to_string(disk_dir()?.join(format!("{}.html", key))).ok()?;
+    remember(key, &html);
+    Some(html)
+}
+
+fn store(key: &str, html: &str) {
+    remember(key, html);
+
+    if let Some(dir) = disk_dir() {
+        let written = fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(dir.join(format!("{}.html", key)), html));
+        if let Err(e) = written {
+            tracing::warn!("Failed to write render cache entry {}: {}", key, e);
+        }
+    }
+}
+
+/// Render markdown to HTML, reusing a cached render of identical content
+pub fn render(markdown: &str) -> String {
+    let key = cache_key(markdown);
+    if let Some(html) = lookup(&key) {
+        return html;
+    }
+
+    let html = reader::markdown_to_html(markdown);
+    store(&key, &html);
+    html
+}
+
+/// Render a stored post, skipping the markdown file entirely on a warm cache
+pub fn render_post(slug: &str) -> Result<String> {
+    let known_key = cache().slugs.get(slug).map(|key| key.clone());
+    if let Some(html) = known_key.as_deref().and_then(lookup) {
+        return Ok(html);
+    }
+
+    let markdown = reader::read_markdown_file(slug)?;
+    let key = cache_key(&markdown);
+    let html = render(&markdown);
+    cache().slugs.insert(slug.to_string(), key);
+    Ok(html)
+}
+
+/// Drop the cached render of a post after it was written or deleted
+pub fn invalidate(slug: &str) {
+    let Some((_, key)) = cache().slugs.remove(slug) else {
+        return;
+    };
+    cache().entries.remove(&key);
+
+    if let Some(dir) = disk_dir() {
+        let _ = fs::remove_file(dir.join(format!("{}.html", key)));
+    }
+}
+
+/// Drop every cached render after uploaded media changed
+///
+/// Renders embed the size and `srcset` of the images they show, which the markdown alone does
+/// not capture.
+pub fn media_changed() {
+    {
+        let mut order = cache().order.lock().unwrap_or_else(PoisonError::into_inner);
+        cache().entries.clear();
+        cache().slugs.clear();
+        order.clear();
+    }
+
+    let Some(entries) = disk_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.path().extension().is_some_and(|extension| extension == "html") {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One test, since clearing the shared cache would race with checks on its contents
+    #[test]
+    fn keeps_a_bounded_number_of_renders_until_media_changes() {
+        for n in 0..=MAX_ENTRIES {
+            render(&format!("bounded cache entry {}", n));
+        }
+        let newest = cache_key(&format!("bounded cache entry {}", MAX_ENTRIES));
+        assert!(cache().entries.len() <= MAX_ENTRIES);
+        assert!(!cache().entries.contains_key(&cache_key("bounded cache entry 0")));
+        assert!(cache().entries.contains_key(&newest));
+
+        media_changed();
+        assert!(!cache().entries.contains_key(&newest));
+    }
+}
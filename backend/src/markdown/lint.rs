@@ -0,0 +1,160 @@
+use markdown::mdast::Node;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Outcome of validating a post: errors block saving, warnings do not
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LintReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl LintReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    pub max_title_chars: usize,
+    pub max_content_bytes: usize,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            max_title_chars: 200,
+            max_content_bytes: 256 * 1024,
+        }
+    }
+}
+
+impl LintOptions {
+    /// Defaults overridden by BLOG_MAX_TITLE_CHARS and BLOG_MAX_CONTENT_BYTES
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok();
+
+        Self {
+            max_title_chars: var("BLOG_MAX_TITLE_CHARS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_title_chars),
+            max_content_bytes: var("BLOG_MAX_CONTENT_BYTES")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_content_bytes),
+        }
+    }
+}
+
+/// Validate a post's title and content before it is saved
+pub fn lint_post(title: &str, content: &str, options: &LintOptions) -> LintReport {
+    let mut report = LintReport::default();
+    check_title(title, options, &mut report);
+    check_content(content, options, &mut report);
+    report
+}
+
+/// Validate content on its own, e.g. for a preview without a title yet
+pub fn lint_content(content: &str, options: &LintOptions) -> LintReport {
+    let mut report = LintReport::default();
+    check_content(content, options, &mut report);
+    report
+}
+
+fn check_title(title: &str, options: &LintOptions, report: &mut LintReport) {
+    let length = title.trim().chars().count();
+    if length == 0 {
+        report.errors.push("Title must not be empty".to_string());
+    } else if length > options.max_title_chars {
+        report.errors.push(format!(
+            "Title is {} characters long, the limit is {}",
+            length, options.max_title_chars
+        ));
+    }
+}
+
+fn check_content(content: &str, options: &LintOptions, report: &mut LintReport) {
+    if content.trim().is_empty() {
+        report.errors.push("Content must not be empty".to_string());
+        return;
+    }
+    if content.len() > options.max_content_bytes {
+        report.errors.push(format!(
+            "Content is {} bytes, the limit is {}",
+            content.len(),
+            options.max_content_bytes
+        ));
+        return;
+    }
+
+    let tree = match markdown::to_mdast(content, &markdown::ParseOptions::default()) {
+        Ok(tree) => tree,
+        Err(e) => {
+            report.errors.push(format!("Content could not be parsed: {}", e));
+            return;
+        }
+    };
+
+    let mut state = WalkState {
+        // The post title renders as the page's <h1>
+        last_depth: 1,
+        headings: HashSet::new(),
+    };
+    walk(&tree, &mut state, report);
+}
+
+struct WalkState {
+    last_depth: u8,
+    headings: HashSet<String>,
+}
+
+fn line_of(node: &Node) -> String {
+    node.position()
+        .map(|p| format!("Line {}: ", p.start.line))
+        .unwrap_or_default()
+}
+
+fn walk(node: &Node, state: &mut WalkState, report: &mut LintReport) {
+    match node {
+        Node::Heading(heading) => {
+            if heading.depth > state.last_depth + 1 {
+                report.warnings.push(format!(
+                    "{}heading level skips from h{} to h{}",
+                    line_of(node),
+                    state.last_depth,
+                    heading.depth
+                ));
+            }
+            state.last_depth = heading.depth;
+
+            let text = node.to_string().trim().to_lowercase();
+            if !text.is_empty() && !state.headings.insert(text) {
+                report.warnings.push(format!(
+                    "{}duplicate heading \"{}\"",
+                    line_of(node),
+                    node.to_string().trim()
+                ));
+            }
+        }
+        Node::Image(image) if image.alt.trim().is_empty() => {
+            report.warnings.push(format!(
+                "{}image {} has no alt text",
+                line_of(node),
+                image.url
+            ));
+        }
+        // The renderer escapes HTML, so it is worth pointing out but not blocking
+        Node::Html(_) => {
+            report.warnings.push(format!(
+                "{}raw HTML will be shown as text",
+                line_of(node)
+            ));
+        }
+        _ => {}
+    }
+
+    for child in node.children().into_iter().flatten() {
+        walk(child, state, report);
+    }
+}
@@ -1,4 +1,7 @@
+pub mod cache;
+pub mod lint;
 pub mod reader;
+pub mod shortcodes;
 pub mod writer;
 
 use chrono::{DateTime, Utc};
@@ -9,7 +12,113 @@ pub struct Post {
     pub slug: String,
     pub title: String,
     pub author: String,
+    /// Keycloak subject of the author, who gets notified of activity on the post
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_sub: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub content: String,
+    /// Language code of the post, e.g. "en" or "fr"
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// Slug of the source post this one translates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation_of: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Author overrides for social sharing metadata
+    #[serde(default, skip_serializing_if = "SeoOverrides::is_empty")]
+    pub seo: SeoOverrides,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<CoverImage>,
+    /// Readers cannot comment on the post when set
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub comments_closed: bool,
+}
+
+/// Image shown above a post and on its card, chosen from uploaded media
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CoverImage {
+    /// `/media/...` URL of the uploaded original
+    pub url: String,
+    #[serde(default)]
+    pub alt: String,
+    /// Point kept in view when the image is cropped, as fractions of its width and height
+    #[serde(default = "centered")]
+    pub focal_x: f32,
+    #[serde(default = "centered")]
+    pub focal_y: f32,
+}
+
+fn centered() -> f32 {
+    0.5
+}
+
+impl CoverImage {
+    /// Build a cover from form input; the focal point is given in percent and defaults to the center
+    pub fn from_form(url: &str, alt: &str, focal_x: &str, focal_y: &str) -> Option<Self> {
+        let url = url.trim();
+        if url.is_empty() {
+            return None;
+        }
+        let fraction = |percent: &str| {
+            percent
+                .trim()
+                .parse::<f32>()
+                .map_or(centered(), |percent| (percent / 100.0).clamp(0.0, 1.0))
+        };
+        Some(Self {
+            url: url.to_string(),
+            alt: alt.trim().to_string(),
+            focal_x: fraction(focal_x),
+            focal_y: fraction(focal_y),
+        })
+    }
+
+    /// CSS `object-position` keeping the focal point in view
+    pub fn object_position(&self) -> String {
+        format!("{:.0}% {:.0}%", self.focal_x * 100.0, self.focal_y * 100.0)
+    }
+}
+
+/// Per-post replacements for the generated OpenGraph and Twitter Card values
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SeoOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl SeoOverrides {
+    /// Build overrides from form input, treating blank fields as unset
+    pub fn from_form(title: &str, description: &str, image: &str) -> Self {
+        let field = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        Self {
+            title: field(title),
+            description: field(description),
+            image: field(image),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image.is_none()
+    }
+}
+
+fn default_lang() -> String {
+    crate::i18n::DEFAULT_LOCALE.to_string()
+}
+
+impl Post {
+    /// Slug shared by a post and all of its translations
+    pub fn translation_group(&self) -> &str {
+        self.translation_of.as_deref().unwrap_or(&self.slug)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
 }
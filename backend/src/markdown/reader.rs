@@ -1,3 +1,4 @@
+use crate::markdown::shortcodes;
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
@@ -16,27 +17,86 @@ pub fn read_markdown_file(slug: &str) -> Result<String> {
     Ok(content)
 }
 
+/// Rendered HTML along with any author-facing warnings
+#[derive(Debug, Clone, Default)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub warnings: Vec<String>,
+}
+
+/// Convert markdown content to HTML, expanding shortcodes
+pub fn render_markdown(markdown: &str) -> RenderedMarkdown {
+    let expanded = shortcodes::expand(markdown);
+    let html = markdown::to_html(&expanded.markdown);
+
+    RenderedMarkdown {
+        html: crate::media::variants::rewrite_images(&expanded.splice(&html)),
+        warnings: expanded.warnings,
+    }
+}
+
 /// Convert markdown content to HTML
 pub fn markdown_to_html(markdown: &str) -> String {
-    markdown::to_html(markdown)
+    render_markdown(markdown).html
 }
 
-/// Read and convert markdown file to HTML
-pub fn read_and_render_markdown(slug: &str) -> Result<String> {
-    let markdown_content = read_markdown_file(slug)?;
-    let html_content = markdown_to_html(&markdown_content);
-    Ok(html_content)
+/// Plain-text summary of markdown content, cut at a word boundary
+pub fn excerpt(markdown: &str, max_chars: usize) -> String {
+    let stripped = shortcodes::strip(markdown);
+    let text = match markdown::to_mdast(&stripped, &markdown::ParseOptions::default()) {
+        Ok(tree) => tree
+            .children()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|block| !matches!(block, markdown::mdast::Node::Code(_)))
+                    .map(|block| block.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default(),
+        Err(_) => stripped,
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = match cut.rfind(' ') {
+        Some(index) if index > 0 => &cut[..index],
+        _ => &cut,
+    };
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
 }
 
-/// Read post data from posts.json
-pub fn read_post(slug: &str) -> Result<crate::markdown::Post> {
+/// Number of words in the readable text of markdown content
+pub fn word_count(markdown: &str) -> usize {
+    excerpt(markdown, usize::MAX).split_whitespace().count()
+}
+
+/// Read all posts from posts.json
+pub fn read_posts() -> Result<Vec<crate::markdown::Post>> {
     let posts_content = fs::read_to_string("posts.json").context("Failed to read posts.json")?;
 
-    let posts: Vec<crate::markdown::Post> =
-        serde_json::from_str(&posts_content).context("Failed to parse posts.json")?;
+    serde_json::from_str(&posts_content).context("Failed to parse posts.json")
+}
 
-    posts
+/// Read post data from posts.json
+pub fn read_post(slug: &str) -> Result<crate::markdown::Post> {
+    read_posts()?
         .into_iter()
         .find(|post| post.slug == slug)
         .ok_or_else(|| anyhow::anyhow!("Post not found: {}", slug))
 }
+
+/// Read a post together with all of its translations, the source post first
+pub fn read_translations(post: &crate::markdown::Post) -> Result<Vec<crate::markdown::Post>> {
+    let group = post.translation_group();
+    let mut translations: Vec<_> = read_posts()?
+        .into_iter()
+        .filter(|p| p.translation_group() == group)
+        .collect();
+    translations.sort_by_key(|p| p.translation_of.is_some());
+    Ok(translations)
+}
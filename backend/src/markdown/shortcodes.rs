@@ -0,0 +1,339 @@
+use crate::utils::escape_html;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+const OPEN: &str = "{{<";
+const CLOSE: &str = ">}}";
+
+/// Arguments passed to a shortcode, e.g. `{{< figure /img.png caption="A cat" >}}`
+#[derive(Debug, Clone, Default)]
+pub struct ShortcodeArgs {
+    pub positional: Vec<String>,
+    pub named: HashMap<String, String>,
+}
+
+impl ShortcodeArgs {
+    /// Look up an argument by name, falling back to its position
+    pub fn get(&self, name: &str, position: usize) -> Option<&str> {
+        self.named
+            .get(name)
+            .or_else(|| self.positional.get(position))
+            .map(String::as_str)
+    }
+}
+
+/// A handler that turns shortcode arguments into safe HTML
+pub trait Shortcode: Send + Sync {
+    fn render(&self, args: &ShortcodeArgs) -> Result<String>;
+}
+
+impl<F> Shortcode for F
+where
+    F: Fn(&ShortcodeArgs) -> Result<String> + Send + Sync,
+{
+    fn render(&self, args: &ShortcodeArgs) -> Result<String> {
+        self(args)
+    }
+}
+
+/// Named collection of shortcode handlers
+#[derive(Clone, Default)]
+pub struct ShortcodeRegistry {
+    handlers: HashMap<String, Arc<dyn Shortcode>>,
+}
+
+impl ShortcodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry preloaded with the built-in `youtube`, `gist` and `figure` shortcodes;
+    /// new shortcodes are registered here
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("youtube", youtube);
+        registry.register("gist", gist);
+        registry.register("figure", figure);
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, handler: impl Shortcode + 'static) {
+        self.handlers.insert(name.to_string(), Arc::new(handler));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Shortcode>> {
+        self.handlers.get(name).cloned()
+    }
+}
+
+fn registry() -> &'static ShortcodeRegistry {
+    static REGISTRY: OnceLock<ShortcodeRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(ShortcodeRegistry::with_builtins)
+}
+
+/// Markdown with shortcodes swapped out for placeholders
+pub struct Expanded {
+    pub markdown: String,
+    pub warnings: Vec<String>,
+    fragments: Vec<String>,
+    nonce: String,
+}
+
+impl Expanded {
+    /// Replace the placeholders in rendered HTML with the shortcode output
+    pub fn splice(&self, html: &str) -> String {
+        let mut html = html.to_string();
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            let marker = placeholder(&self.nonce, index);
+            html = html.replace(&format!("<p>{}</p>", marker), fragment);
+            html = html.replace(&marker, fragment);
+        }
+        html
+    }
+}
+
+fn placeholder(nonce: &str, index: usize) -> String {
+    format!("shortcode{}x{}x", nonce, index)
+}
+
+/// Expand every shortcode outside code blocks and inline code using the global registry
+pub fn expand(markdown: &str) -> Expanded {
+    let registry = registry();
+    let mut expanded = Expanded {
+        markdown: String::with_capacity(markdown.len()),
+        warnings: Vec::new(),
+        fragments: Vec::new(),
+        nonce: format!("{:016x}", rand::random::<u64>()),
+    };
+    let mut in_fence = false;
+    let mut in_indented = false;
+    let mut previous_blank = true;
+
+    for (line_no, line) in markdown.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        let blank = trimmed.is_empty();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        // Indented code blocks start after a blank line and go on while lines stay indented
+        in_indented = !in_fence
+            && ((is_indented(line) && (previous_blank || in_indented)) || (blank && in_indented));
+        previous_blank = blank;
+        if in_fence || in_indented || !line.contains(OPEN) {
+            expanded.markdown.push_str(line);
+            continue;
+        }
+
+        let spans = code_spans(line);
+        let mut pos = 0;
+        while let Some(found) = line[pos..].find(OPEN) {
+            let start = pos + found;
+            // Shortcodes inside inline code are shown as written
+            if let Some(&(_, end)) = spans.iter().find(|(from, to)| (*from..*to).contains(&start)) {
+                expanded.markdown.push_str(&line[pos..end]);
+                pos = end;
+                continue;
+            }
+            let Some(len) = line[start..].find(CLOSE) else {
+                break;
+            };
+            let source = &line[start..start + len + CLOSE.len()];
+            let inner = &source[OPEN.len()..source.len() - CLOSE.len()];
+            expanded.markdown.push_str(&line[pos..start]);
+
+            let mut tokens = tokenize(inner).into_iter();
+            let name = tokens.next().unwrap_or_default();
+            let args = parse_args(tokens);
+
+            match registry.get(&name).map(|handler| handler.render(&args)) {
+                Some(Ok(html)) => {
+                    let index = expanded.fragments.len();
+                    expanded.fragments.push(html);
+                    expanded.markdown.push_str(&placeholder(&expanded.nonce, index));
+                }
+                Some(Err(e)) => {
+                    expanded.warnings.push(format!(
+                        "Line {}: shortcode `{}` failed: {}",
+                        line_no + 1,
+                        name,
+                        e
+                    ));
+                    expanded.markdown.push_str(source);
+                }
+                None => {
+                    expanded.warnings.push(format!(
+                        "Line {}: unknown shortcode `{}`",
+                        line_no + 1,
+                        name
+                    ));
+                    expanded.markdown.push_str(source);
+                }
+            }
+
+            pos = start + source.len();
+        }
+        expanded.markdown.push_str(&line[pos..]);
+    }
+
+    expanded
+}
+
+fn is_indented(line: &str) -> bool {
+    line.starts_with("    ") || line.starts_with('\t')
+}
+
+/// Byte ranges of the inline code spans on a line, backticks included
+fn code_spans(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let run_at = |i: usize| bytes[i..].iter().take_while(|b| **b == b'`').count();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        // A span closes at the next run of exactly as many backticks
+        let open = run_at(i);
+        let mut j = i + open;
+        let mut close = None;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let run = run_at(j);
+                if run == open {
+                    close = Some(j + run);
+                    break;
+                }
+                j += run;
+            } else {
+                j += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                spans.push((i, end));
+                i = end;
+            }
+            // Unmatched backticks are literal
+            None => i += open,
+        }
+    }
+    spans
+}
+
+/// Remove shortcodes entirely, e.g. when deriving plain-text excerpts
+pub fn strip(markdown: &str) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(start) = rest.find(OPEN) {
+        let Some(len) = rest[start..].find(CLOSE) else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        rest = &rest[start + len + CLOSE.len()..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Split shortcode contents on whitespace, keeping double-quoted values together
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+fn parse_args(tokens: impl Iterator<Item = String>) -> ShortcodeArgs {
+    let mut args = ShortcodeArgs::default();
+    for token in tokens {
+        match token.split_once('=') {
+            Some((key, value)) if is_identifier(key) => {
+                args.named.insert(key.to_string(), value.to_string());
+            }
+            _ => args.positional.push(token),
+        }
+    }
+    args
+}
+
+fn is_identifier(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Accept only absolute http(s) URLs or site-relative paths
+fn is_safe_url(url: &str) -> bool {
+    url.starts_with("https://")
+        || url.starts_with("http://")
+        || (url.starts_with('/') && !url.starts_with("//"))
+}
+
+fn youtube(args: &ShortcodeArgs) -> Result<String> {
+    let id = args.get("id", 0).ok_or_else(|| anyhow!("missing video id"))?;
+    if !is_identifier(id) {
+        return Err(anyhow!("invalid video id `{}`", id));
+    }
+
+    Ok(format!(
+        r#"<div class="embed embed-youtube"><iframe src="https://www.youtube-nocookie.com/embed/{}" title="YouTube video" loading="lazy" allow="accelerometer; encrypted-media; picture-in-picture" allowfullscreen></iframe></div>"#,
+        id
+    ))
+}
+
+fn gist(args: &ShortcodeArgs) -> Result<String> {
+    let reference = args
+        .get("id", 0)
+        .ok_or_else(|| anyhow!("missing gist reference, expected user/id"))?;
+    let (user, id) = reference
+        .split_once('/')
+        .filter(|(user, id)| is_identifier(user) && is_identifier(id))
+        .ok_or_else(|| anyhow!("invalid gist reference `{}`, expected user/id", reference))?;
+
+    Ok(format!(
+        r#"<div class="embed embed-gist"><script src="https://gist.github.com/{0}/{1}.js"></script><noscript><a href="https://gist.github.com/{0}/{1}">View gist {0}/{1}</a></noscript></div>"#,
+        user, id
+    ))
+}
+
+fn figure(args: &ShortcodeArgs) -> Result<String> {
+    let src = args.get("src", 0).ok_or_else(|| anyhow!("missing image src"))?;
+    if !is_safe_url(src) {
+        return Err(anyhow!("unsupported image src `{}`", src));
+    }
+    let caption = args.get("caption", 1).unwrap_or_default();
+    let alt = args.get("alt", 2).unwrap_or(caption);
+
+    let mut html = format!(
+        r#"<figure class="figure"><img src="{}" alt="{}" loading="lazy">"#,
+        escape_html(src),
+        escape_html(alt)
+    );
+    if !caption.is_empty() {
+        html.push_str(&format!("<figcaption>{}</figcaption>", escape_html(caption)));
+    }
+    html.push_str("</figure>");
+
+    Ok(html)
+}
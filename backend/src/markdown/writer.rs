@@ -1,4 +1,4 @@
-use crate::markdown::Post;
+use crate::markdown::{cache, Post};
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
@@ -32,17 +32,32 @@ pub fn create_post(post: &Post) -> Result<()> {
     // Update posts.json
     update_posts_json(post, true)?;
 
+    cache::invalidate(&post.slug);
+    crate::activitypub::post_created(post);
+    crate::webmention::post_published(post, None);
+    crate::newsletter::post_published(post);
+    crate::media::library::post_saved(post);
+    crate::notifications::post_published(post);
+
     Ok(())
 }
 
 /// Update an existing post
 pub fn update_post(post: &Post) -> Result<()> {
+    // Links removed by the edit are notified too
+    let previous = crate::markdown::reader::read_post(&post.slug).ok();
+
     // Write markdown file
     write_markdown_file(&post.slug, &post.content)?;
 
     // Update posts.json
     update_posts_json(post, false)?;
 
+    cache::invalidate(&post.slug);
+    crate::activitypub::post_updated(post);
+    crate::webmention::post_published(post, previous.as_ref());
+    crate::media::library::post_saved(post);
+
     Ok(())
 }
 
@@ -77,6 +92,8 @@ fn update_posts_json(post: &Post, is_new: bool) -> Result<()> {
 
 /// Delete a post
 pub fn delete_post(slug: &str) -> Result<()> {
+    let previous = crate::markdown::reader::read_post(slug).ok();
+
     // Delete markdown file
     let file_path = Path::new(POSTS_DIR).join(format!("{}.md", slug));
     if file_path.exists() {
@@ -89,6 +106,16 @@ pub fn delete_post(slug: &str) -> Result<()> {
     // Update posts.json
     remove_post_from_json(slug)?;
 
+    cache::invalidate(slug);
+    crate::activitypub::post_deleted(slug);
+    if let Some(previous) = &previous {
+        crate::webmention::post_deleted(previous);
+    }
+    crate::media::library::post_deleted(slug);
+    crate::comments::post_deleted(slug);
+    crate::reactions::post_deleted(slug);
+    crate::notifications::post_deleted(slug);
+
     Ok(())
 }
 
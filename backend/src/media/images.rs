@@ -0,0 +1,212 @@
+use crate::media::sniff::MediaKind;
+use anyhow::{anyhow, Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+/// Quality used when an original has to be re-encoded to apply its rotation
+const ORIGINAL_QUALITY: u8 = 92;
+/// Images wider or taller than this are refused before any pixel is decoded
+const MAX_DIMENSION: u32 = 16_384;
+/// Memory a decoder may allocate, so a small file declaring a huge canvas cannot exhaust it
+const MAX_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Kinds we decode and re-encode; GIFs keep their animation and AVIF has no decoder here
+pub fn is_processable(kind: MediaKind) -> bool {
+    matches!(kind, MediaKind::Jpeg | MediaKind::Png | MediaKind::Webp)
+}
+
+fn format_of(kind: MediaKind) -> Result<ImageFormat> {
+    match kind {
+        MediaKind::Jpeg => Ok(ImageFormat::Jpeg),
+        MediaKind::Png => Ok(ImageFormat::Png),
+        MediaKind::Webp => Ok(ImageFormat::WebP),
+        _ => Err(anyhow!("{} images cannot be processed", kind.mime())),
+    }
+}
+
+/// Decode an image with its EXIF rotation applied
+pub fn decode(bytes: &[u8], kind: MediaKind) -> Result<DynamicImage> {
+    let (mut image, orientation) = decode_raw(bytes, kind)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn decode_raw(bytes: &[u8], kind: MediaKind) -> Result<(DynamicImage, Orientation)> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format_of(kind)?);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().context("Failed to read image")?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let image = DynamicImage::from_decoder(decoder).context("Failed to decode image")?;
+    Ok((image, orientation))
+}
+
+/// Encode an image; `quality` applies to the lossy formats
+pub fn encode(image: &DynamicImage, kind: MediaKind, quality: u8) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match kind {
+        MediaKind::Jpeg => {
+            // JPEG has no alpha channel
+            JpegEncoder::new_with_quality(&mut bytes, quality)
+                .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+                .context("Failed to encode JPEG")?;
+        }
+        MediaKind::Png => {
+            image
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+                .context("Failed to encode PNG")?;
+        }
+        MediaKind::Webp => {
+            let rgba = image.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode(quality as f32);
+            bytes.extend_from_slice(&encoded);
+        }
+        _ => return Err(anyhow!("{} images cannot be encoded", kind.mime())),
+    }
+    Ok(bytes)
+}
+
+/// Remove EXIF, XMP and text metadata, which can carry GPS coordinates and camera serials
+///
+/// Images are only re-encoded when their EXIF rotation has to be baked into the pixels;
+/// otherwise the metadata blocks are dropped and the image data is kept byte for byte.
+pub fn strip_metadata(bytes: &[u8], kind: MediaKind) -> Result<Vec<u8>> {
+    if !is_processable(kind) {
+        return Ok(bytes.to_vec());
+    }
+
+    let (mut image, orientation) = decode_raw(bytes, kind)?;
+    if orientation != Orientation::NoTransforms {
+        image.apply_orientation(orientation);
+        return encode(&image, kind, ORIGINAL_QUALITY);
+    }
+
+    match kind {
+        MediaKind::Jpeg => strip_jpeg(bytes),
+        MediaKind::Png => strip_png(bytes),
+        MediaKind::Webp => strip_webp(bytes),
+        _ => Ok(bytes.to_vec()),
+    }
+}
+
+/// Drop APP1 (EXIF, XMP), APP13 (IPTC) and comment segments before the image data
+fn strip_jpeg(bytes: &[u8]) -> Result<Vec<u8>> {
+    let malformed = || anyhow!("Malformed JPEG");
+    let mut output = bytes[..2].to_vec();
+    let mut position = 2;
+
+    loop {
+        let marker = *bytes.get(position + 1).ok_or_else(malformed)?;
+        if bytes[position] != 0xFF {
+            return Err(malformed());
+        }
+        // Fill bytes before a marker
+        if marker == 0xFF {
+            position += 1;
+            continue;
+        }
+        // Start of scan: the rest is entropy-coded data and the end marker
+        if marker == 0xDA {
+            output.extend_from_slice(&bytes[position..]);
+            return Ok(output);
+        }
+
+        let length_bytes = bytes.get(position + 2..position + 4).ok_or_else(malformed)?;
+        let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        let end = position + 2 + length;
+        if length < 2 || end > bytes.len() {
+            return Err(malformed());
+        }
+        if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+            output.extend_from_slice(&bytes[position..end]);
+        }
+        position = end;
+    }
+}
+
+/// Drop the eXIf chunk and text chunks, which hold free-form metadata
+fn strip_png(bytes: &[u8]) -> Result<Vec<u8>> {
+    const SIGNATURE_LEN: usize = 8;
+    let mut output = bytes[..SIGNATURE_LEN].to_vec();
+    let mut position = SIGNATURE_LEN;
+
+    while position < bytes.len() {
+        let header = bytes
+            .get(position..position + 8)
+            .ok_or_else(|| anyhow!("Malformed PNG"))?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        // Length, type, data and CRC
+        let end = position + 12 + length;
+        if end > bytes.len() {
+            return Err(anyhow!("Malformed PNG"));
+        }
+        if !matches!(&header[4..8], b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            output.extend_from_slice(&bytes[position..end]);
+        }
+        position = end;
+    }
+    Ok(output)
+}
+
+/// Drop the EXIF and XMP chunks and clear their flags in the extended header
+fn strip_webp(bytes: &[u8]) -> Result<Vec<u8>> {
+    const HEADER_LEN: usize = 12;
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    let mut output = bytes[..HEADER_LEN].to_vec();
+    let mut position = HEADER_LEN;
+
+    while position < bytes.len() {
+        let header = bytes
+            .get(position..position + 8)
+            .ok_or_else(|| anyhow!("Malformed WebP"))?;
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        // Chunks are padded to an even size
+        let end = (position + 8 + length + length % 2).min(bytes.len());
+        if position + 8 + length > bytes.len() {
+            return Err(anyhow!("Malformed WebP"));
+        }
+
+        match &header[..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let start = output.len();
+                output.extend_from_slice(&bytes[position..end]);
+                if let Some(flags) = output.get_mut(start + 8) {
+                    *flags &= !(EXIF_FLAG | XMP_FLAG);
+                }
+            }
+            _ => output.extend_from_slice(&bytes[position..end]),
+        }
+        position = end;
+    }
+
+    let riff_size = (output.len() - 8) as u32;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_images_declaring_a_huge_canvas() {
+        let small = encode(&DynamicImage::new_rgb8(1, 1), MediaKind::Jpeg, 80).unwrap();
+        assert!(strip_metadata(&small, MediaKind::Jpeg).is_ok());
+
+        // Claim 65535 × 65535 pixels in the start-of-frame header
+        let mut bomb = small.clone();
+        let frame = bomb.windows(2).position(|marker| marker == [0xFF, 0xC0]).unwrap();
+        bomb[frame + 5..frame + 9].copy_from_slice(&[0xFF; 4]);
+        assert!(strip_metadata(&bomb, MediaKind::Jpeg).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use crate::markdown::Post;
+use crate::media::sniff::MediaKind;
+use crate::media::store;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const LIBRARY_FILE: &str = "media_library.json";
+
+/// Who uploaded a file and when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Upload {
+    pub original_name: String,
+    pub uploaded_by: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Library {
+    /// Upload details by stored file name
+    #[serde(default)]
+    uploads: BTreeMap<String, Upload>,
+    /// Stored files each post links to, by slug
+    #[serde(default)]
+    references: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// A stored original as listed in the media library
+#[derive(Debug, Clone, Serialize)]
+pub struct Asset {
+    pub file: String,
+    pub url: String,
+    pub mime: &'static str,
+    /// Size of the original in bytes, resized copies not included
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Unknown for files uploaded before the library kept track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload: Option<Upload>,
+    /// Slugs of the posts that link to the file
+    pub posts: Vec<String>,
+}
+
+/// Result of asking to delete a stored file
+pub enum Deletion {
+    Deleted,
+    NotFound,
+    /// Refused because these posts still link to it
+    InUse(Vec<String>),
+}
+
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn read() -> Result<Library> {
+    let path = Path::new(LIBRARY_FILE);
+    if !path.exists() {
+        return Ok(Library::default());
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read media_library.json")?;
+    serde_json::from_str(&content).context("Failed to parse media_library.json")
+}
+
+fn write(library: &Library) -> Result<()> {
+    let content = serde_json::to_string_pretty(library).context("Failed to serialize media library")?;
+    std::fs::write(LIBRARY_FILE, content).context("Failed to write media_library.json")
+}
+
+/// Stored originals, without the resized copies derived from them
+fn originals() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(store::media_dir()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| store::is_valid_name(name) && !name.contains('-'))
+        .collect();
+    files.sort();
+
+    // A WebP next to a JPEG or PNG of the same hash is the full-size copy of that image
+    let hashes_with_source: BTreeSet<String> = files
+        .iter()
+        .filter(|name| !name.ends_with(".webp"))
+        .filter_map(|name| name.split_once('.').map(|(hash, _)| hash.to_string()))
+        .collect();
+    files.retain(|name| {
+        name.strip_suffix(".webp")
+            .is_none_or(|hash| !hashes_with_source.contains(hash))
+    });
+    files
+}
+
+/// The original a `/media` URL points at, resolving resized copies to their source
+fn original_of(name: &str, originals: &BTreeSet<String>) -> Option<String> {
+    let hash = name.split(['-', '.']).next()?;
+    MediaKind::ALL
+        .into_iter()
+        .map(|kind| format!("{}.{}", hash, kind.extension()))
+        .find(|file| originals.contains(file))
+}
+
+/// Stored files a post links to, in its content, as its sharing image or as its cover
+fn references_in(post: &Post, originals: &BTreeSet<String>) -> BTreeSet<String> {
+    let prefix = store::url("");
+    let prefix = prefix.as_str();
+    let sources = [
+        Some(post.content.as_str()),
+        post.seo.image.as_deref(),
+        post.cover.as_ref().map(|cover| cover.url.as_str()),
+    ];
+
+    sources
+        .into_iter()
+        .flatten()
+        .flat_map(|text| text.match_indices(prefix).map(move |(at, _)| &text[at + prefix.len()..]))
+        .filter_map(|rest| {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+                .unwrap_or(rest.len());
+            original_of(rest[..end].trim_end_matches('.'), originals)
+        })
+        .collect()
+}
+
+/// Remember who uploaded a file, the first upload of identical content wins
+pub fn record_upload(file: &str, original_name: &str, uploaded_by: &str) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut library = read()?;
+    library.uploads.entry(file.to_string()).or_insert_with(|| Upload {
+        original_name: original_name.to_string(),
+        uploaded_by: uploaded_by.to_string(),
+        uploaded_at: Utc::now(),
+    });
+    write(&library)
+}
+
+/// Called by `markdown::writer` when a post is created or edited
+pub fn post_saved(post: &Post) {
+    let originals: BTreeSet<String> = originals().into_iter().collect();
+    let references = references_in(post, &originals);
+
+    let _guard = lock().lock().unwrap();
+    let result = read().and_then(|mut library| {
+        if references.is_empty() {
+            library.references.remove(&post.slug);
+        } else {
+            library.references.insert(post.slug.clone(), references);
+        }
+        write(&library)
+    });
+    if let Err(e) = result {
+        tracing::error!("Failed to track media used by {}: {}", post.slug, e);
+    }
+}
+
+/// Called by `markdown::writer` when a post is deleted
+pub fn post_deleted(slug: &str) {
+    let _guard = lock().lock().unwrap();
+    let result = read().and_then(|mut library| {
+        if library.references.remove(slug).is_some() {
+            write(&library)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        tracing::error!("Failed to drop media references of {}: {}", slug, e);
+    }
+}
+
+/// Recompute every post's references from scratch, e.g. for posts written before tracking
+pub fn rebuild_references() -> Result<()> {
+    let originals: BTreeSet<String> = originals().into_iter().collect();
+    // Failing here matters: garbage collection would otherwise see every file as unused
+    let posts = crate::markdown::reader::read_posts()?;
+    let references: BTreeMap<String, BTreeSet<String>> = posts
+        .iter()
+        .map(|post| (post.slug.clone(), references_in(post, &originals)))
+        .filter(|(_, files)| !files.is_empty())
+        .collect();
+
+    let _guard = lock().lock().unwrap();
+    let mut library = read()?;
+    library.references = references;
+    write(&library)
+}
+
+/// Every stored original with its details and the posts using it
+pub fn assets() -> Result<Vec<Asset>> {
+    let library = {
+        let _guard = lock().lock().unwrap();
+        read()?
+    };
+    let dir = store::media_dir();
+
+    Ok(originals()
+        .into_iter()
+        .filter_map(|file| {
+            let kind = MediaKind::from_extension(file.rsplit_once('.')?.1)?;
+            let path = dir.join(&file);
+            let size = std::fs::metadata(&path).ok()?.len();
+            let dimensions = image::image_dimensions(&path).ok();
+            let posts = library
+                .references
+                .iter()
+                .filter(|(_, files)| files.contains(&file))
+                .map(|(slug, _)| slug.clone())
+                .collect();
+
+            Some(Asset {
+                url: store::url(&file),
+                mime: kind.mime(),
+                size,
+                width: dimensions.map(|(width, _)| width),
+                height: dimensions.map(|(_, height)| height),
+                upload: library.uploads.get(&file).cloned(),
+                posts,
+                file,
+            })
+        })
+        .collect())
+}
+
+/// Remove an original and every copy derived from it
+fn remove_files(file: &str) -> Result<()> {
+    let Some((hash, _)) = file.split_once('.') else {
+        return Ok(());
+    };
+    let dir = store::media_dir();
+    for entry in std::fs::read_dir(&dir).context("Failed to read media directory")? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if name.starts_with(&format!("{}.", hash)) || name.starts_with(&format!("{}-", hash)) {
+            std::fs::remove_file(dir.join(&name)).with_context(|| format!("Failed to delete {}", name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete a stored file, refusing while posts link to it unless `force` is set
+pub fn delete(file: &str, force: bool) -> Result<Deletion> {
+    if !originals().iter().any(|original| original == file) {
+        return Ok(Deletion::NotFound);
+    }
+
+    let _guard = lock().lock().unwrap();
+    let mut library = read()?;
+    let posts: Vec<String> = library
+        .references
+        .iter()
+        .filter(|(_, files)| files.contains(file))
+        .map(|(slug, _)| slug.clone())
+        .collect();
+    if !posts.is_empty() && !force {
+        return Ok(Deletion::InUse(posts));
+    }
+
+    remove_files(file)?;
+    library.uploads.remove(file);
+    for files in library.references.values_mut() {
+        files.remove(file);
+    }
+    library.references.retain(|_, files| !files.is_empty());
+    write(&library)?;
+    crate::markdown::cache::media_changed();
+    Ok(Deletion::Deleted)
+}
+
+/// Uploads younger than this are kept, they may belong to a post still being written
+fn gc_grace_period() -> chrono::Duration {
+    let hours = std::env::var("BLOG_MEDIA_GC_GRACE_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(24);
+    chrono::Duration::hours(hours)
+}
+
+/// `backend media-gc [--dry-run]`: delete media no post links to
+pub fn collect_garbage(dry_run: bool) -> Result<()> {
+    rebuild_references()?;
+    let cutoff = Utc::now() - gc_grace_period();
+
+    let orphans: Vec<Asset> = assets()?
+        .into_iter()
+        .filter(|asset| asset.posts.is_empty())
+        .filter(|asset| asset.upload.as_ref().is_none_or(|upload| upload.uploaded_at < cutoff))
+        .collect();
+
+    let mut freed = 0;
+    for asset in &orphans {
+        println!("{} {} ({} bytes)", if dry_run { "would delete" } else { "deleting" }, asset.file, asset.size);
+        if !dry_run {
+            delete(&asset.file, false)?;
+        }
+        freed += asset.size;
+    }
+
+    println!(
+        "🧹 {} orphaned media files, {} bytes{}",
+        orphans.len(),
+        freed,
+        if dry_run { " (dry run, nothing deleted)" } else { " freed" }
+    );
+    Ok(())
+}
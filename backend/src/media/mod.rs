@@ -0,0 +1,347 @@
+pub mod images;
+pub mod library;
+pub mod sniff;
+pub mod store;
+pub mod variants;
+
+use crate::auth::jwt::Claims;
+use crate::i18n::Locale;
+use crate::utils::escape_html;
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, Json, Response},
+    Extension,
+};
+use library::{Asset, Deletion};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Stored names change with the content, so responses can be cached for good
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Room for the multipart boundaries and the other form fields around the file
+const FORM_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// Largest accepted upload in bytes, from BLOG_MEDIA_MAX_BYTES
+pub fn max_upload_bytes() -> usize {
+    std::env::var("BLOG_MEDIA_MAX_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse().ok())
+        .unwrap_or(10 * 1024 * 1024)
+}
+
+/// Request body limit for the upload route, above axum's 2 MB default
+pub fn upload_body_limit() -> usize {
+    max_upload_bytes() + FORM_OVERHEAD_BYTES
+}
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(json!({ "success": false, "message": message.into() })),
+    )
+}
+
+/// Markdown that embeds an image, or links to any other file
+pub fn markdown_snippet(file: &str, label: &str, is_image: bool) -> String {
+    let label = label.replace('[', "\\[").replace(']', "\\]");
+    if is_image {
+        format!("![{}]({})", label, store::url(file))
+    } else {
+        format!("[{}]({})", label, store::url(file))
+    }
+}
+
+/// Stored original a `/media` URL points at, with its kind
+fn stored_original(url: &str) -> Option<(String, sniff::MediaKind)> {
+    let file = url.strip_prefix(store::url("").as_str())?;
+    if file.contains('-') || !store::media_dir().join(file).is_file() {
+        return None;
+    }
+    let kind = sniff::MediaKind::from_extension(file.rsplit_once('.')?.1)?;
+    store::is_valid_name(file).then(|| (file.to_string(), kind))
+}
+
+/// Whether a URL is an uploaded image, as required for cover images
+pub fn is_uploaded_image(url: &str) -> bool {
+    stored_original(url).is_some_and(|(_, kind)| kind.is_image())
+}
+
+/// Size in bytes and MIME type of an uploaded file, for feed enclosures
+pub fn file_info(url: &str) -> Option<(u64, &'static str)> {
+    let (file, kind) = stored_original(url)?;
+    let size = std::fs::metadata(store::media_dir().join(file)).ok()?.len();
+    Some((size, kind.mime()))
+}
+
+/// Width and height of an uploaded image
+pub fn image_dimensions(url: &str) -> Option<(u32, u32)> {
+    let (file, _) = stored_original(url)?;
+    image::image_dimensions(store::media_dir().join(file)).ok()
+}
+
+/// `<img>` of a cover image cropped around its focal point, with the srcset of its resized copies
+///
+/// The cover at the top of a post is usually the largest element on screen, so it is not lazy.
+pub fn cover_img(cover: &crate::markdown::CoverImage, class: &str, sizes: &str, eager: bool) -> String {
+    variants::rewrite_images(&format!(
+        r#"<img src="{}" alt="{}" class="{}" style="object-position: {}" sizes="{}" loading="{}">"#,
+        escape_html(&cover.url),
+        escape_html(&cover.alt),
+        class,
+        cover.object_position(),
+        sizes,
+        if eager { "eager" } else { "lazy" }
+    ))
+}
+
+/// `/admin/media`: store an uploaded file and return the snippet to paste into a post
+///
+/// Expects a `file` part and an optional `alt` text; the name of the file is used otherwise.
+pub async fn upload(
+    Extension(claims): Extension<Claims>,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let max_bytes = max_upload_bytes();
+    let mut upload: Option<(String, Vec<u8>)> = None;
+    let mut alt: Option<String> = None;
+
+    let multipart_error =
+        |e: axum::extract::multipart::MultipartError| api_error(e.status(), e.body_text());
+
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("file") => {
+                let name = field.file_name().unwrap_or_default().to_string();
+                let mut bytes = Vec::new();
+                while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+                    if bytes.len() + chunk.len() > max_bytes {
+                        return Err(api_error(
+                            StatusCode::PAYLOAD_TOO_LARGE,
+                            format!("Files are limited to {} bytes", max_bytes),
+                        ));
+                    }
+                    bytes.extend_from_slice(&chunk);
+                }
+                upload = Some((name, bytes));
+            }
+            Some("alt") => {
+                alt = Some(field.text().await.map_err(multipart_error)?.trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    let Some((name, bytes)) = upload else {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Missing file field"));
+    };
+    if bytes.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "The file is empty"));
+    }
+    let Some(kind) = sniff::sniff(&bytes) else {
+        return Err(api_error(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Only JPEG, PNG, GIF, WebP, AVIF, MP4 and PDF files can be uploaded",
+        ));
+    };
+
+    let bytes = tokio::task::spawn_blocking(move || images::strip_metadata(&bytes, kind))
+        .await
+        .map_err(|_| api_error(StatusCode::INTERNAL_SERVER_ERROR, "The file could not be processed"))?
+        .map_err(|e| {
+            tracing::warn!("Rejected upload {}: {}", name, e);
+            api_error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "The image could not be read")
+        })?;
+
+    let file = store::save(&bytes, kind).map_err(|e| {
+        tracing::error!("Failed to store upload {}: {}", name, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, "The file could not be stored")
+    })?;
+    if let Err(e) = library::record_upload(&file, &name, &claims.sub) {
+        tracing::error!("Failed to record upload {}: {}", file, e);
+    }
+    variants::spawn_generate(file.clone());
+
+    let label = alt.filter(|alt| !alt.is_empty()).unwrap_or_else(|| {
+        name.rsplit_once('.')
+            .map_or(name.as_str(), |(stem, _)| stem)
+            .to_string()
+    });
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "File uploaded",
+        "url": store::url(&file),
+        "mime": kind.mime(),
+        "size": bytes.len(),
+        "markdown": markdown_snippet(&file, &label, kind.is_image()),
+    })))
+}
+
+/// `/media/{file}`: serve an upload with its sniffed MIME type
+///
+/// Missing resized copies are generated on first request.
+pub async fn serve(Path(file): Path<String>, headers: HeaderMap) -> Result<Response, StatusCode> {
+    let stored = match store::read(&file) {
+        Some(stored) => Some(stored),
+        None => match variants::source_of(&file) {
+            Some(source) => {
+                tokio::task::spawn_blocking(move || variants::generate(&source))
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                    .map_err(|e| {
+                        tracing::warn!("Failed to generate {}: {}", file, e);
+                        StatusCode::NOT_FOUND
+                    })?;
+                store::read(&file)
+            }
+            None => None,
+        },
+    };
+    let (bytes, kind) = stored.ok_or(StatusCode::NOT_FOUND)?;
+    let etag = format!("\"{}\"", file);
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    let builder = Response::builder()
+        .header(header::CACHE_CONTROL, CACHE_CONTROL)
+        .header(header::ETAG, &etag);
+
+    let response = if not_modified {
+        builder.status(StatusCode::NOT_MODIFIED).body(Body::empty())
+    } else {
+        builder
+            .header(header::CONTENT_TYPE, kind.mime())
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .body(Body::from(bytes))
+    };
+    response.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// `/admin/media/library`: every stored file with its details and the posts using it
+pub async fn library() -> Result<Json<serde_json::Value>, StatusCode> {
+    let assets = library::assets().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(json!({ "success": true, "assets": assets })))
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+fn render_asset(asset: &Asset, locale: &Locale) -> String {
+    let preview = if asset.mime.starts_with("image/") {
+        format!(
+            r#"<img src="{}" alt="" loading="lazy">"#,
+            escape_html(&asset.url)
+        )
+    } else {
+        String::new()
+    };
+    let dimensions = match (asset.width, asset.height) {
+        (Some(width), Some(height)) => format!("{} × {}", width, height),
+        _ => "—".to_string(),
+    };
+    let uploader = asset.upload.as_ref().map_or("—".to_string(), |upload| {
+        format!(
+            "{}<br><small>{}</small>",
+            escape_html(&upload.uploaded_by),
+            locale.format_date(&upload.uploaded_at)
+        )
+    });
+    let posts = if asset.posts.is_empty() {
+        format!(r#"<span class="media-unused">{}</span>"#, locale.t("media.unused"))
+    } else {
+        asset
+            .posts
+            .iter()
+            .map(|slug| format!(r#"<a href="/posts/{0}">{0}</a>"#, escape_html(slug)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    format!(
+        r#"<tr id="asset-{id}">
+    <td class="media-preview">{preview}</td>
+    <td><a href="{url}">{name}</a><br><small>{mime}</small></td>
+    <td>{size}</td>
+    <td>{dimensions}</td>
+    <td>{uploader}</td>
+    <td>{posts}</td>
+    <td><button class="btn btn-secondary" onclick="deleteAsset('{file}', {in_use})">{delete}</button></td>
+</tr>"#,
+        id = escape_html(asset.file.split('.').next().unwrap_or_default()),
+        preview = preview,
+        url = escape_html(&asset.url),
+        name = escape_html(
+            asset
+                .upload
+                .as_ref()
+                .map_or(asset.file.as_str(), |upload| upload.original_name.as_str())
+        ),
+        mime = asset.mime,
+        size = format_size(asset.size),
+        dimensions = dimensions,
+        uploader = uploader,
+        posts = posts,
+        file = escape_html(&asset.file),
+        in_use = !asset.posts.is_empty(),
+        delete = locale.t("media.delete"),
+    )
+}
+
+/// `/admin/media/library/html`: the library as table rows for the HTMX page
+pub async fn library_html(locale: Locale) -> Result<Html<String>, StatusCode> {
+    let assets = library::assets().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if assets.is_empty() {
+        return Ok(Html(format!(
+            r#"<tr><td colspan="7" class="empty-state">{}</td></tr>"#,
+            locale.t("media.empty")
+        )));
+    }
+    Ok(Html(assets.iter().map(|asset| render_asset(asset, &locale)).collect()))
+}
+
+/// `/admin/media`: the media library page, its rows are loaded with the author's token
+pub async fn serve_library_page(locale: Locale) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/admin/media.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Html(locale.localize(&template)))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+/// `DELETE /admin/media/{file}`: refused with 409 while posts use the file, unless `?force=true`
+pub async fn delete(
+    Path(file): Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    match library::delete(&file, query.force) {
+        Ok(Deletion::Deleted) => Ok(Json(json!({ "success": true, "message": "File deleted" }))),
+        Ok(Deletion::NotFound) => Err(api_error(StatusCode::NOT_FOUND, "No such file")),
+        Ok(Deletion::InUse(posts)) => Err((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "success": false,
+                "message": "The file is used by posts, delete with force=true to remove it anyway",
+                "posts": posts,
+            })),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to delete {}: {}", file, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "The file could not be deleted"))
+        }
+    }
+}
@@ -0,0 +1,87 @@
+/// A media type accepted for upload, recognised from the leading bytes of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Avif,
+    Mp4,
+    Pdf,
+}
+
+impl MediaKind {
+    /// Every accepted kind, used to map stored extensions back to a MIME type
+    pub const ALL: [MediaKind; 7] = [
+        MediaKind::Jpeg,
+        MediaKind::Png,
+        MediaKind::Gif,
+        MediaKind::Webp,
+        MediaKind::Avif,
+        MediaKind::Mp4,
+        MediaKind::Pdf,
+    ];
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            MediaKind::Jpeg => "image/jpeg",
+            MediaKind::Png => "image/png",
+            MediaKind::Gif => "image/gif",
+            MediaKind::Webp => "image/webp",
+            MediaKind::Avif => "image/avif",
+            MediaKind::Mp4 => "video/mp4",
+            MediaKind::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            MediaKind::Jpeg => "jpg",
+            MediaKind::Png => "png",
+            MediaKind::Gif => "gif",
+            MediaKind::Webp => "webp",
+            MediaKind::Avif => "avif",
+            MediaKind::Mp4 => "mp4",
+            MediaKind::Pdf => "pdf",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.extension() == extension)
+    }
+
+    pub fn is_image(self) -> bool {
+        self.mime().starts_with("image/")
+    }
+}
+
+/// Detect the media kind from magic bytes, ignoring the name and type the client sent
+///
+/// SVG and HTML are deliberately not recognised: served from our origin they could run scripts.
+pub fn sniff(bytes: &[u8]) -> Option<MediaKind> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(MediaKind::Jpeg);
+    }
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(MediaKind::Png);
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some(MediaKind::Gif);
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some(MediaKind::Webp);
+    }
+    if bytes.starts_with(b"%PDF-") {
+        return Some(MediaKind::Pdf);
+    }
+
+    // ISO base media files start with a `ftyp` box naming the major brand
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return match &bytes[8..12] {
+            b"avif" | b"avis" => Some(MediaKind::Avif),
+            b"isom" | b"iso2" | b"mp41" | b"mp42" | b"avc1" | b"M4V " | b"dash" => Some(MediaKind::Mp4),
+            _ => None,
+        };
+    }
+    None
+}
@@ -0,0 +1,62 @@
+use crate::media::sniff::MediaKind;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Directory uploads are stored in, from BLOG_MEDIA_DIR
+pub fn media_dir() -> PathBuf {
+    std::env::var("BLOG_MEDIA_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("media"))
+}
+
+/// Public URL path of a stored file
+pub fn url(file: &str) -> String {
+    format!("/media/{}", file)
+}
+
+/// Names we hand out are a hex digest, optional variant suffixes and a known extension,
+/// which also keeps requests from reaching outside the media directory
+pub fn is_valid_name(file: &str) -> bool {
+    let Some((stem, extension)) = file.rsplit_once('.') else {
+        return false;
+    };
+    MediaKind::from_extension(extension).is_some()
+        && !stem.is_empty()
+        && stem
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Store bytes under a name derived from their SHA-256, so identical uploads share one file
+pub fn save(bytes: &[u8], kind: MediaKind) -> Result<String> {
+    let file = format!("{:x}.{}", Sha256::digest(bytes), kind.extension());
+    if media_dir().join(&file).exists() {
+        return Ok(file);
+    }
+
+    write(&file, bytes)?;
+    Ok(file)
+}
+
+/// Write a file into the media directory
+pub fn write(file: &str, bytes: &[u8]) -> Result<()> {
+    let dir = media_dir();
+    std::fs::create_dir_all(&dir).context("Failed to create media directory")?;
+    // Write under a temporary name first so a crash never leaves a truncated file behind
+    let partial = dir.join(format!(".{}.partial", file));
+    std::fs::write(&partial, bytes).context("Failed to write media file")?;
+    std::fs::rename(&partial, dir.join(file)).context("Failed to store media file")
+}
+
+/// Contents and kind of a stored file
+pub fn read(file: &str) -> Option<(Vec<u8>, MediaKind)> {
+    if !is_valid_name(file) {
+        return None;
+    }
+    let kind = MediaKind::from_extension(file.rsplit_once('.')?.1)?;
+    let bytes = std::fs::read(media_dir().join(file)).ok()?;
+    Some((bytes, kind))
+}
@@ -0,0 +1,218 @@
+use crate::media::images;
+use crate::media::sniff::MediaKind;
+use crate::media::store;
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+
+/// Quality of resized JPEG and WebP files
+const VARIANT_QUALITY: u8 = 80;
+/// Layout width the `sizes` attribute is based on, the width of the page container
+const CONTENT_WIDTH: u32 = 1200;
+
+/// Widths of the resized copies, from BLOG_MEDIA_WIDTHS
+pub fn widths() -> Vec<u32> {
+    let mut widths: Vec<u32> = std::env::var("BLOG_MEDIA_WIDTHS")
+        .unwrap_or_else(|_| "480,960,1920".to_string())
+        .split(',')
+        .filter_map(|width| width.trim().parse().ok())
+        .filter(|width| *width > 0)
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+    widths
+}
+
+/// `<hash>-<width>w.<ext>`, or `<hash>.<ext>` for the full-size copy
+fn variant_name(hash: &str, width: Option<u32>, kind: MediaKind) -> String {
+    match width {
+        Some(width) => format!("{}-{}w.{}", hash, width, kind.extension()),
+        None => format!("{}.{}", hash, kind.extension()),
+    }
+}
+
+/// Split a stored original name into its hash and kind, if it is an image we resize
+fn original(file: &str) -> Option<(&str, MediaKind)> {
+    let (hash, extension) = file.rsplit_once('.')?;
+    let kind = MediaKind::from_extension(extension)?;
+    let is_hash = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+    (is_hash && images::is_processable(kind)).then_some((hash, kind))
+}
+
+/// Every file derived from an original of the given width, with its width and kind
+fn planned(hash: &str, kind: MediaKind, width: u32) -> Vec<(String, Option<u32>, MediaKind)> {
+    let mut files = Vec::new();
+    for variant_width in widths().into_iter().filter(|w| *w < width) {
+        files.push((variant_name(hash, Some(variant_width), kind), Some(variant_width), kind));
+        if kind != MediaKind::Webp {
+            files.push((
+                variant_name(hash, Some(variant_width), MediaKind::Webp),
+                Some(variant_width),
+                MediaKind::Webp,
+            ));
+        }
+    }
+    if kind != MediaKind::Webp {
+        files.push((variant_name(hash, None, MediaKind::Webp), None, MediaKind::Webp));
+    }
+    files
+}
+
+/// Write the resized and WebP copies of an uploaded image that do not exist yet
+pub fn generate(file: &str) -> Result<()> {
+    let Some((hash, kind)) = original(file) else {
+        return Ok(());
+    };
+    let dir = store::media_dir();
+    let bytes = std::fs::read(dir.join(file)).context("Failed to read original image")?;
+    let image = images::decode(&bytes, kind)?;
+
+    let mut written = false;
+    for (name, width, variant_kind) in planned(hash, kind, image.width()) {
+        if dir.join(&name).exists() {
+            continue;
+        }
+        let resized = match width {
+            Some(width) => image.resize(width, u32::MAX, FilterType::Lanczos3),
+            None => image.clone(),
+        };
+        store::write(&name, &images::encode(&resized, variant_kind, VARIANT_QUALITY)?)?;
+        written = true;
+    }
+    if written {
+        crate::markdown::cache::media_changed();
+    }
+    Ok(())
+}
+
+/// Generate variants without holding up the caller
+pub fn spawn_generate(file: String) {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    handle.spawn_blocking(move || {
+        if let Err(e) = generate(&file) {
+            tracing::warn!("Failed to generate variants of {}: {}", file, e);
+        }
+    });
+}
+
+/// Generate missing variants of every stored image, e.g. before a static export
+pub fn generate_all() -> Result<()> {
+    let Ok(entries) = std::fs::read_dir(store::media_dir()) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if original(&name).is_some() {
+            if let Err(e) = generate(&name) {
+                tracing::warn!("Failed to generate variants of {}: {}", name, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The original a variant name is derived from, when the variant is one we would generate
+///
+/// Lets `/media` create variants lazily, e.g. for images uploaded before they existed.
+pub fn source_of(file: &str) -> Option<String> {
+    let (stem, extension) = file.rsplit_once('.')?;
+    let variant_kind = MediaKind::from_extension(extension)?;
+    let (hash, width) = match stem.split_once('-') {
+        Some((hash, width)) => (hash, Some(width.strip_suffix('w')?.parse::<u32>().ok()?)),
+        None => (stem, None),
+    };
+    if width.is_some_and(|width| !widths().contains(&width)) {
+        return None;
+    }
+
+    let dir = store::media_dir();
+    [MediaKind::Jpeg, MediaKind::Png, MediaKind::Webp]
+        .into_iter()
+        .map(|kind| (kind, variant_name(hash, None, kind)))
+        .find(|(_, name)| original(name).is_some() && dir.join(name).exists())
+        .filter(|(kind, _)| match width {
+            // Resized copies exist in the original format and as WebP
+            Some(_) => variant_kind == *kind || variant_kind == MediaKind::Webp,
+            // The only full-size copy is the WebP one
+            None => variant_kind == MediaKind::Webp && *kind != MediaKind::Webp,
+        })
+        .map(|(_, name)| name)
+}
+
+/// `srcset`, `sizes`, `width` and `height` for a local image, if it is one we resize
+fn responsive_attributes(file: &str) -> Option<Vec<(&'static str, String)>> {
+    let (hash, _) = original(file)?;
+    let (width, height) = image::image_dimensions(store::media_dir().join(file)).ok()?;
+
+    let mut sources: Vec<String> = widths()
+        .into_iter()
+        .filter(|w| *w < width)
+        .map(|w| format!("{} {}w", store::url(&variant_name(hash, Some(w), MediaKind::Webp)), w))
+        .collect();
+    sources.push(format!(
+        "{} {}w",
+        store::url(&variant_name(hash, None, MediaKind::Webp)),
+        width
+    ));
+
+    Some(vec![
+        ("srcset", sources.join(", ")),
+        (
+            "sizes",
+            format!("(max-width: {0}px) 100vw, {0}px", CONTENT_WIDTH.min(width)),
+        ),
+        ("width", width.to_string()),
+        ("height", height.to_string()),
+        ("loading", "lazy".to_string()),
+        ("decoding", "async".to_string()),
+    ])
+}
+
+/// Value of an attribute in the source of a single tag
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + tag[start..].find('"')?;
+    Some(&tag[start..end])
+}
+
+/// Give `<img>` tags of uploaded images a WebP `srcset` and their intrinsic size
+///
+/// Attributes already on the tag, such as `loading` from the figure shortcode, are kept.
+pub fn rewrite_images(html: &str) -> String {
+    let prefix = store::url("");
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find("<img ") {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        let end = start + length;
+        let tag = &rest[start..end];
+        output.push_str(&rest[..start]);
+
+        let attributes = attribute(tag, "src")
+            .and_then(|src| src.strip_prefix(prefix.as_str()))
+            .and_then(responsive_attributes);
+        match attributes {
+            Some(attributes) => {
+                let (body, self_closing) = match tag.strip_suffix('/') {
+                    Some(body) => (body.trim_end(), " /"),
+                    None => (tag, ""),
+                };
+                output.push_str(body);
+                for (name, value) in attributes {
+                    if attribute(tag, name).is_none() {
+                        output.push_str(&format!(" {}=\"{}\"", name, value));
+                    }
+                }
+                output.push_str(self_closing);
+            }
+            None => output.push_str(tag),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
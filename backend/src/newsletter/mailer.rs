@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// An email ready to be handed to the SMTP transport
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingMail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    /// One-click unsubscribe link advertised in the List-Unsubscribe headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsubscribe_url: Option<String>,
+}
+
+fn from_address() -> String {
+    std::env::var("BLOG_NEWSLETTER_FROM")
+        .unwrap_or_else(|_| format!("{} <newsletter@localhost>", crate::utils::site_title()))
+}
+
+/// SMTP transport configured from the BLOG_SMTP_* variables
+fn build_transport() -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let host = std::env::var("BLOG_SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+    let port = std::env::var("BLOG_SMTP_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(25);
+    let security = std::env::var("BLOG_SMTP_SECURITY").unwrap_or_else(|_| "none".to_string());
+
+    let mut builder = match security.as_str() {
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+            .context("Invalid SMTP host")?,
+        "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+            .context("Invalid SMTP host")?,
+        _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+    }
+    .port(port);
+
+    // env.example leaves both empty, which means the relay takes mail without logging in
+    let username = std::env::var("BLOG_SMTP_USERNAME").unwrap_or_default();
+    let password = std::env::var("BLOG_SMTP_PASSWORD").unwrap_or_default();
+    if !username.is_empty() && !password.is_empty() {
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+    Ok(builder.build())
+}
+
+fn transport() -> Result<&'static AsyncSmtpTransport<Tokio1Executor>> {
+    static TRANSPORT: OnceLock<AsyncSmtpTransport<Tokio1Executor>> = OnceLock::new();
+    if let Some(transport) = TRANSPORT.get() {
+        return Ok(transport);
+    }
+    let transport = build_transport()?;
+    Ok(TRANSPORT.get_or_init(|| transport))
+}
+
+fn build_message(mail: &OutgoingMail) -> Result<Message> {
+    let from: Mailbox = from_address().parse().context("Invalid BLOG_NEWSLETTER_FROM")?;
+    let to: Mailbox = mail.to.parse().context("Invalid recipient address")?;
+
+    let mut builder = Message::builder().from(from).to(to).subject(&mail.subject);
+    if let Some(url) = &mail.unsubscribe_url {
+        builder = builder
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                format!("<{}>", url),
+            ))
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                "List-Unsubscribe=One-Click".to_string(),
+            ));
+    }
+
+    builder
+        .multipart(MultiPart::alternative_plain_html(
+            mail.text.clone(),
+            mail.html.clone(),
+        ))
+        .context("Failed to build email")
+}
+
+/// Hands emails over for delivery
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: &OutgoingMail) -> Result<()>;
+}
+
+/// Mailer used outside of tests, sending through the configured SMTP relay
+struct SmtpMailer;
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: &OutgoingMail) -> Result<()> {
+        let message = build_message(mail)?;
+        transport()?
+            .send(message)
+            .await
+            .context(format!("Failed to send email to {}", mail.to))?;
+        Ok(())
+    }
+}
+
+pub fn mailer() -> &'static dyn Mailer {
+    &SmtpMailer
+}
@@ -0,0 +1,437 @@
+pub mod mailer;
+pub mod queue;
+pub mod store;
+
+use crate::i18n::Locale;
+use crate::markdown::Post;
+use crate::utils::escape_html;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Form, Path},
+    http::{HeaderMap, StatusCode},
+    response::Html,
+};
+use mailer::OutgoingMail;
+use serde::Deserialize;
+use store::{Subscriber, SubscriberStatus};
+
+const EMAIL_TEMPLATES: &str = "../frontend/templates/email";
+const EXCERPT_CHARS: usize = 300;
+/// A pending address is sent the confirmation email again only after this long
+const CONFIRMATION_COOLDOWN_HOURS: i64 = 1;
+/// Subscription attempts from one address within the spam module's rate window
+const SUBSCRIBE_RATE_LIMIT: usize = 5;
+
+/// Whether each post is mailed on its own or collected into a periodic digest
+fn digest_mode() -> bool {
+    std::env::var("BLOG_NEWSLETTER_MODE").is_ok_and(|mode| mode == "digest")
+}
+
+fn digest_interval() -> chrono::Duration {
+    let hours = std::env::var("BLOG_NEWSLETTER_DIGEST_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(24);
+    chrono::Duration::hours(hours)
+}
+
+fn locale_of(subscriber: &Subscriber) -> Locale {
+    Locale(crate::i18n::normalize(&subscriber.lang).unwrap_or_else(crate::i18n::default_locale))
+}
+
+fn unsubscribe_url(subscriber: &Subscriber) -> String {
+    format!(
+        "{}/newsletter/unsubscribe/{}",
+        crate::utils::public_base_url(),
+        subscriber.unsubscribe_token
+    )
+}
+
+/// Render the text and HTML parts of an email template
+///
+/// Values are escaped in the HTML part; `{site}` inside translated messages is the blog title.
+fn render_email(
+    name: &str,
+    locale: &Locale,
+    values: &[(&str, String)],
+    html_values: &[(&str, String)],
+) -> Result<(String, String)> {
+    let read = |extension: &str| {
+        std::fs::read_to_string(format!("{}/{}.{}", EMAIL_TEMPLATES, name, extension))
+            .context(format!("Failed to read email template {}.{}", name, extension))
+    };
+    let site = crate::utils::site_title();
+
+    let mut text = locale.localize_text(&read("txt")?).replace("{site}", &site);
+    let mut html = locale.localize(&read("html")?).replace("{site}", &escape_html(&site));
+    for (key, value) in values {
+        let placeholder = format!("{{{{ {} }}}}", key);
+        text = text.replace(&placeholder, value);
+        html = html.replace(&placeholder, &escape_html(value));
+    }
+    for (key, value) in html_values {
+        html = html.replace(&format!("{{{{ {} | safe }}}}", key), value);
+    }
+    Ok((text, html))
+}
+
+fn confirmation_mail(subscriber: &Subscriber) -> Result<OutgoingMail> {
+    let locale = locale_of(subscriber);
+    let confirm_url = format!(
+        "{}/newsletter/confirm/{}",
+        crate::utils::public_base_url(),
+        subscriber.confirm_token
+    );
+    let (text, html) = render_email("confirm", &locale, &[("confirm_url", confirm_url)], &[])?;
+
+    Ok(OutgoingMail {
+        to: subscriber.email.clone(),
+        subject: locale
+            .t("newsletter.confirm_subject")
+            .replace("{site}", &crate::utils::site_title()),
+        text,
+        html,
+        unsubscribe_url: None,
+    })
+}
+
+fn post_mail(subscriber: &Subscriber, post: &Post) -> Result<OutgoingMail> {
+    let locale = locale_of(subscriber);
+    let unsubscribe = unsubscribe_url(subscriber);
+    let (text, html) = render_email(
+        "post",
+        &locale,
+        &[
+            ("title", post.title.clone()),
+            ("author", post.author.clone()),
+            ("date", locale.format_date(&post.created_at)),
+            ("excerpt", crate::markdown::reader::excerpt(&post.content, EXCERPT_CHARS)),
+            ("url", crate::feeds::post_url(post)),
+            ("unsubscribe_url", unsubscribe.clone()),
+        ],
+        &[("content", crate::feeds::post_html(post))],
+    )?;
+
+    Ok(OutgoingMail {
+        to: subscriber.email.clone(),
+        subject: locale.t("newsletter.post_subject").replace("{title}", &post.title),
+        text,
+        html,
+        unsubscribe_url: Some(unsubscribe),
+    })
+}
+
+fn digest_mail(subscriber: &Subscriber, posts: &[Post]) -> Result<OutgoingMail> {
+    let locale = locale_of(subscriber);
+    let unsubscribe = unsubscribe_url(subscriber);
+
+    let text_list: String = posts
+        .iter()
+        .map(|post| {
+            format!(
+                "{}\n{}\n{}\n\n",
+                post.title,
+                crate::markdown::reader::excerpt(&post.content, EXCERPT_CHARS),
+                crate::feeds::post_url(post)
+            )
+        })
+        .collect();
+    let html_list: String = posts
+        .iter()
+        .map(|post| {
+            format!(
+                r#"<li><a href="{}">{}</a><p>{}</p></li>"#,
+                escape_html(&crate::feeds::post_url(post)),
+                escape_html(&post.title),
+                escape_html(&crate::markdown::reader::excerpt(&post.content, EXCERPT_CHARS))
+            )
+        })
+        .collect();
+
+    let (text, html) = render_email(
+        "digest",
+        &locale,
+        &[("posts", text_list), ("unsubscribe_url", unsubscribe.clone())],
+        &[("posts", format!("<ul>{}</ul>", html_list))],
+    )?;
+
+    Ok(OutgoingMail {
+        to: subscriber.email.clone(),
+        subject: locale
+            .t("newsletter.digest_subject")
+            .replace("{site}", &crate::utils::site_title()),
+        text,
+        html,
+        unsubscribe_url: Some(unsubscribe),
+    })
+}
+
+/// Called by `markdown::writer` when a post is created
+///
+/// Posts have no draft state, so creating one is what publishes it; edits are not mailed.
+/// Translations are not mailed again, subscribers already heard about the original.
+pub fn post_published(post: &Post) {
+    if post.translation_of.is_some() {
+        return;
+    }
+
+    let result = if digest_mode() {
+        store::add_to_digest(&post.slug)
+    } else {
+        store::confirmed_subscribers().and_then(|subscribers| {
+            let mails = subscribers
+                .iter()
+                .map(|subscriber| post_mail(subscriber, post))
+                .collect::<Result<Vec<_>>>()?;
+            queue::enqueue(mails)
+        })
+    };
+    if let Err(e) = result {
+        tracing::error!("Failed to queue newsletter for {}: {}", post.slug, e);
+    }
+}
+
+/// Queue the digest once enough time has passed since its first post
+pub fn send_digest_if_due() -> Result<()> {
+    let Some(digest) = store::take_digest_if_due(digest_interval())? else {
+        return Ok(());
+    };
+
+    // Posts deleted in the meantime are left out
+    let posts: Vec<Post> = digest
+        .slugs
+        .iter()
+        .filter_map(|slug| crate::markdown::reader::read_post(slug).ok())
+        .collect();
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    let mails = store::confirmed_subscribers()?
+        .iter()
+        .map(|subscriber| digest_mail(subscriber, &posts))
+        .collect::<Result<Vec<_>>>()?;
+    queue::enqueue(mails)
+}
+
+#[derive(Deserialize)]
+pub struct SubscribeForm {
+    email: String,
+}
+
+fn message(class: &str, text: &str) -> Html<String> {
+    Html(format!(
+        r#"<p class="message {}">{}</p>"#,
+        class,
+        escape_html(text)
+    ))
+}
+
+/// `/newsletter/subscribe`: start a subscription and send the confirmation email
+///
+/// Attempts are rate limited per address and confirmations are not resent right away, so the form
+/// cannot be used to flood someone else's inbox.
+pub async fn subscribe(
+    headers: HeaderMap,
+    locale: Locale,
+    Form(form): Form<SubscribeForm>,
+) -> Html<String> {
+    if crate::spam::rate_limited("newsletter", &headers, SUBSCRIBE_RATE_LIMIT) {
+        return message("error", &locale.t("newsletter.too_many_attempts"));
+    }
+    let email = form.email.trim();
+    if email.parse::<lettre::Address>().is_err() {
+        return message("error", &locale.t("newsletter.invalid_email"));
+    }
+
+    let cooldown = chrono::Duration::hours(CONFIRMATION_COOLDOWN_HOURS);
+    let result = store::subscribe(email, locale.code(), cooldown).and_then(|subscriber| {
+        // Every case gets the same answer, so the form does not reveal who subscribed
+        if let Some(subscriber) = subscriber {
+            queue::enqueue(vec![confirmation_mail(&subscriber)?])?;
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => message("success", &locale.t("newsletter.check_inbox")),
+        Err(e) => {
+            tracing::error!("Failed to subscribe {}: {}", email, e);
+            message("error", &locale.t("newsletter.error"))
+        }
+    }
+}
+
+fn render_page(locale: &Locale, text: &str, form: &str) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/newsletter.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Html(
+        locale
+            .localize(&template)
+            .replace("{{ message }}", &escape_html(text))
+            .replace("{{ form | safe }}", form),
+    ))
+}
+
+/// `/newsletter/confirm/{token}`: the link from the confirmation email
+pub async fn confirm(Path(token): Path<String>, locale: Locale) -> Result<Html<String>, StatusCode> {
+    match store::confirm(&token) {
+        Ok(Some(_)) => render_page(&locale, &locale.t("newsletter.confirmed"), ""),
+        Ok(None) => render_page(&locale, &locale.t("newsletter.invalid_link"), ""),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// `GET /newsletter/unsubscribe/{token}`: the link from the email asks before unsubscribing,
+/// since mail scanners open links on their own
+pub async fn unsubscribe_page(
+    Path(token): Path<String>,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    match store::by_unsubscribe_token(&token) {
+        Ok(Some(subscriber)) if subscriber.status == SubscriberStatus::Unsubscribed => {
+            render_page(&locale, &locale.t("newsletter.unsubscribed"), "")
+        }
+        Ok(Some(_)) => render_page(
+            &locale,
+            &locale.t("newsletter.unsubscribe_confirm"),
+            &format!(
+                r#"<form method="post"><button type="submit" class="btn btn-primary">{}</button></form>"#,
+                escape_html(&locale.t("newsletter.unsubscribe"))
+            ),
+        ),
+        Ok(None) => render_page(&locale, &locale.t("newsletter.invalid_link"), ""),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// `POST /newsletter/unsubscribe/{token}`: the confirmation form, or a one-click List-Unsubscribe-Post
+pub async fn unsubscribe(Path(token): Path<String>, locale: Locale) -> Result<Html<String>, StatusCode> {
+    match store::unsubscribe(&token) {
+        Ok(Some(_)) => render_page(&locale, &locale.t("newsletter.unsubscribed"), ""),
+        Ok(None) => render_page(&locale, &locale.t("newsletter.invalid_link"), ""),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ScratchDir, StubMailer};
+
+    fn headers(ip: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", ip.parse().unwrap());
+        headers
+    }
+
+    async fn subscribe_as(ip: &str, email: &str) -> String {
+        let form = Form(SubscribeForm {
+            email: email.to_string(),
+        });
+        subscribe(headers(ip), Locale("en"), form).await.0
+    }
+
+    fn subscriber(email: &str) -> Subscriber {
+        store::subscribers()
+            .unwrap()
+            .into_iter()
+            .find(|subscriber| subscriber.email == email)
+            .unwrap()
+    }
+
+    /// Hand every queued mail to a stub and return what it received
+    async fn drain_queue() -> Vec<OutgoingMail> {
+        let mailer = StubMailer::default();
+        while queue::send_next(&mailer).await.unwrap() {}
+        mailer.sent()
+    }
+
+    fn post() -> Post {
+        serde_json::from_value(serde_json::json!({
+            "slug": "hello",
+            "title": "Hello",
+            "author": "Alice",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "content": "First post",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_are_mailed_only_after_confirming() {
+        let _dir = ScratchDir::new().await;
+        subscribe_as("192.0.2.1", "reader@example.com").await;
+        let pending = subscriber("reader@example.com");
+        assert_eq!(pending.status, SubscriberStatus::Pending);
+
+        let mails = drain_queue().await;
+        assert_eq!(mails.len(), 1);
+        assert!(mails[0]
+            .text
+            .contains(&format!("/newsletter/confirm/{}", pending.confirm_token)));
+
+        post_published(&post());
+        assert!(drain_queue().await.is_empty());
+
+        let page = confirm(Path(pending.confirm_token.clone()), Locale("en")).await.unwrap();
+        assert!(page.0.contains(&escape_html(&Locale("en").t("newsletter.confirmed"))));
+        assert_eq!(subscriber("reader@example.com").status, SubscriberStatus::Confirmed);
+
+        post_published(&post());
+        let mails = drain_queue().await;
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].to, "reader@example.com");
+        assert_eq!(mails[0].unsubscribe_url, Some(unsubscribe_url(&pending)));
+    }
+
+    #[tokio::test]
+    async fn tokens_only_work_for_their_own_step() {
+        let _dir = ScratchDir::new().await;
+        subscribe_as("192.0.2.2", "reader@example.com").await;
+        let pending = subscriber("reader@example.com");
+        drain_queue().await;
+
+        // The unsubscribe token cannot confirm, and a wrong token changes nothing
+        assert!(store::confirm(&pending.unsubscribe_token).unwrap().is_none());
+        assert!(store::confirm("not-a-token").unwrap().is_none());
+        assert!(store::unsubscribe("not-a-token").unwrap().is_none());
+        assert!(store::confirm(&pending.confirm_token).unwrap().is_some());
+
+        // Opening the link only asks, the form submission unsubscribes
+        let page = unsubscribe_page(Path(pending.unsubscribe_token.clone()), Locale("en")).await.unwrap();
+        assert!(page.0.contains(r#"<form method="post">"#));
+        assert_eq!(subscriber("reader@example.com").status, SubscriberStatus::Confirmed);
+        let page = unsubscribe(Path(pending.unsubscribe_token.clone()), Locale("en")).await.unwrap();
+        assert!(page.0.contains(&escape_html(&Locale("en").t("newsletter.unsubscribed"))));
+        assert_eq!(subscriber("reader@example.com").status, SubscriberStatus::Unsubscribed);
+
+        post_published(&post());
+        assert!(drain_queue().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn confirmations_are_not_resent_during_the_cooldown() {
+        let _dir = ScratchDir::new().await;
+        subscribe_as("192.0.2.3", "reader@example.com").await;
+        subscribe_as("192.0.2.3", "reader@example.com").await;
+        assert_eq!(drain_queue().await.len(), 1);
+
+        // Once the cooldown has passed the email goes out again
+        let resent = store::subscribe("reader@example.com", "en", chrono::Duration::zero()).unwrap();
+        assert!(resent.is_some());
+    }
+
+    #[tokio::test]
+    async fn subscribing_is_rate_limited_per_address() {
+        let _dir = ScratchDir::new().await;
+        for n in 0..SUBSCRIBE_RATE_LIMIT {
+            subscribe_as("192.0.2.4", &format!("reader{}@example.com", n)).await;
+        }
+        let answer = subscribe_as("192.0.2.4", "one-more@example.com").await;
+
+        assert!(answer.contains(&escape_html(&Locale("en").t("newsletter.too_many_attempts"))));
+        assert_eq!(drain_queue().await.len(), SUBSCRIBE_RATE_LIMIT);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::newsletter::mailer::{self, Mailer, OutgoingMail};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const QUEUE_FILE: &str = "mail_queue.json";
+/// Delays before each retry of a failed send
+const RETRY_DELAYS_SECS: [i64; 4] = [60, 5 * 60, 30 * 60, 2 * 60 * 60];
+/// How often the worker looks for due mail when the queue is idle
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedMail {
+    id: String,
+    mail: OutgoingMail,
+    attempts: usize,
+    next_attempt_at: DateTime<Utc>,
+}
+
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn read() -> Result<Vec<QueuedMail>> {
+    let path = Path::new(QUEUE_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read mail_queue.json")?;
+    serde_json::from_str(&content).context("Failed to parse mail_queue.json")
+}
+
+fn write(queue: &[QueuedMail]) -> Result<()> {
+    let content = serde_json::to_string_pretty(queue).context("Failed to serialize mail queue")?;
+    std::fs::write(QUEUE_FILE, content).context("Failed to write mail_queue.json")
+}
+
+/// Queue emails for the background worker; they survive restarts
+pub fn enqueue(mails: Vec<OutgoingMail>) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut queue = read()?;
+    queue.extend(mails.into_iter().map(|mail| QueuedMail {
+        id: uuid::Uuid::new_v4().to_string(),
+        mail,
+        attempts: 0,
+        next_attempt_at: Utc::now(),
+    }));
+    write(&queue)
+}
+
+fn next_due() -> Result<Option<QueuedMail>> {
+    let _guard = lock().lock().unwrap();
+    let now = Utc::now();
+    Ok(read()?
+        .into_iter()
+        .filter(|queued| queued.next_attempt_at <= now)
+        .min_by_key(|queued| queued.next_attempt_at))
+}
+
+/// Remove a sent mail, or schedule its next attempt until retries run out
+fn record_attempt(id: &str, error: Option<&anyhow::Error>) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut queue = read()?;
+
+    match error {
+        None => queue.retain(|queued| queued.id != id),
+        Some(error) => {
+            if let Some(queued) = queue.iter_mut().find(|queued| queued.id == id) {
+                match RETRY_DELAYS_SECS.get(queued.attempts) {
+                    Some(delay) => {
+                        tracing::warn!("Email to {} failed, retrying: {}", queued.mail.to, error);
+                        queued.attempts += 1;
+                        queued.next_attempt_at = Utc::now() + chrono::Duration::seconds(*delay);
+                    }
+                    None => {
+                        tracing::error!("Giving up email to {}: {}", queued.mail.to, error);
+                        let id = queued.id.clone();
+                        queue.retain(|queued| queued.id != id);
+                    }
+                }
+            }
+        }
+    }
+    write(&queue)
+}
+
+/// Pause between two sends, from BLOG_SMTP_RATE_PER_MINUTE
+fn send_interval() -> Duration {
+    let per_minute = std::env::var("BLOG_SMTP_RATE_PER_MINUTE")
+        .ok()
+        .and_then(|rate| rate.parse::<u64>().ok())
+        .filter(|rate| *rate > 0)
+        .unwrap_or(60);
+    Duration::from_millis(60_000 / per_minute)
+}
+
+/// Send the mail that has been due the longest, returning whether there was one
+pub async fn send_next(mailer: &dyn Mailer) -> Result<bool> {
+    let Some(queued) = next_due()? else {
+        return Ok(false);
+    };
+    let result = mailer.send(&queued.mail).await;
+    record_attempt(&queued.id, result.as_ref().err())?;
+    Ok(true)
+}
+
+/// Send queued mail one at a time, pausing `interval` after each, and release digests when due
+async fn work(mailer: &dyn Mailer, interval: Duration) {
+    loop {
+        if let Err(e) = crate::newsletter::send_digest_if_due() {
+            tracing::error!("Failed to prepare newsletter digest: {}", e);
+        }
+
+        let pause = match send_next(mailer).await {
+            Ok(true) => interval,
+            Ok(false) => POLL_INTERVAL,
+            Err(e) => {
+                tracing::error!("Failed to process mail queue: {}", e);
+                POLL_INTERVAL
+            }
+        };
+        tokio::time::sleep(pause).await;
+    }
+}
+
+/// Start the worker at the configured rate
+pub fn spawn_worker() {
+    tokio::spawn(work(mailer::mailer(), send_interval()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ScratchDir, StubMailer};
+
+    fn mail(to: &str) -> OutgoingMail {
+        OutgoingMail {
+            to: to.to_string(),
+            subject: "Hello".to_string(),
+            text: "Hello".to_string(),
+            html: "<p>Hello</p>".to_string(),
+            unsubscribe_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_sends_are_retried_later_then_dropped() {
+        let _dir = ScratchDir::new().await;
+        let mailer = StubMailer::failing();
+        enqueue(vec![mail("reader@example.com")]).unwrap();
+
+        assert!(send_next(&mailer).await.unwrap());
+        let queue = read().unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].attempts, 1);
+        assert!(queue[0].next_attempt_at > Utc::now());
+        // Not due again until the retry delay has passed
+        assert!(!send_next(&mailer).await.unwrap());
+        assert_eq!(mailer.sent().len(), 1);
+
+        let error = anyhow::anyhow!("still failing");
+        for _ in 1..RETRY_DELAYS_SECS.len() {
+            record_attempt(&queue[0].id, Some(&error)).unwrap();
+        }
+        assert_eq!(read().unwrap().len(), 1);
+        record_attempt(&queue[0].id, Some(&error)).unwrap();
+        assert!(read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sent_mail_leaves_the_queue() {
+        let _dir = ScratchDir::new().await;
+        let mailer = StubMailer::default();
+        enqueue(vec![mail("reader@example.com")]).unwrap();
+
+        assert!(send_next(&mailer).await.unwrap());
+        assert!(read().unwrap().is_empty());
+        assert!(!send_next(&mailer).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn the_worker_pauses_between_sends() {
+        let _dir = ScratchDir::new().await;
+        let mailer = StubMailer::default();
+        let interval = Duration::from_millis(200);
+        enqueue(vec![mail("a@example.com"), mail("b@example.com"), mail("c@example.com")]).unwrap();
+
+        let _ = tokio::time::timeout(Duration::from_millis(900), work(&mailer, interval)).await;
+
+        let sent_at = mailer.sent_at();
+        assert_eq!(sent_at.len(), 3);
+        for pair in sent_at.windows(2) {
+            assert!(pair[1] - pair[0] >= interval);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const SUBSCRIBERS_FILE: &str = "subscribers.json";
+const DIGEST_FILE: &str = "newsletter_digest.json";
+const TOKEN_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriberStatus {
+    /// Waiting for the reader to click the confirmation link
+    Pending,
+    Confirmed,
+    Unsubscribed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscriber {
+    pub email: String,
+    pub status: SubscriberStatus,
+    pub confirm_token: String,
+    pub unsubscribe_token: String,
+    pub lang: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// When the last confirmation email was queued, none for subscriptions from before this was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_sent_at: Option<DateTime<Utc>>,
+}
+
+/// Posts published since the last digest went out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingDigest {
+    pub slugs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+}
+
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn read<T: DeserializeOwned + Default>(file: &str) -> Result<T> {
+    let path = Path::new(file);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = std::fs::read_to_string(path).context(format!("Failed to read {}", file))?;
+    serde_json::from_str(&content).context(format!("Failed to parse {}", file))
+}
+
+fn write<T: Serialize>(file: &str, value: &T) -> Result<()> {
+    let content = serde_json::to_string_pretty(value).context(format!("Failed to serialize {}", file))?;
+    std::fs::write(file, content).context(format!("Failed to write {}", file))
+}
+
+pub fn subscribers() -> Result<Vec<Subscriber>> {
+    let _guard = lock().lock().unwrap();
+    read(SUBSCRIBERS_FILE)
+}
+
+pub fn confirmed_subscribers() -> Result<Vec<Subscriber>> {
+    Ok(subscribers()?
+        .into_iter()
+        .filter(|subscriber| subscriber.status == SubscriberStatus::Confirmed)
+        .collect())
+}
+
+/// Register an address, returning the subscription when a confirmation email should go out
+///
+/// A pending address is only sent the email again once `resend_after` has passed since the last one.
+pub fn subscribe(email: &str, lang: &str, resend_after: chrono::Duration) -> Result<Option<Subscriber>> {
+    let _guard = lock().lock().unwrap();
+    let mut subscribers: Vec<Subscriber> = read(SUBSCRIBERS_FILE)?;
+    let now = Utc::now();
+
+    if let Some(existing) = subscribers
+        .iter_mut()
+        .find(|subscriber| subscriber.email.eq_ignore_ascii_case(email))
+    {
+        match existing.status {
+            SubscriberStatus::Confirmed => return Ok(None),
+            // Coming back after unsubscribing requires confirming again
+            SubscriberStatus::Unsubscribed => {
+                existing.status = SubscriberStatus::Pending;
+                existing.confirm_token = crate::utils::random_token(TOKEN_LEN);
+                existing.lang = lang.to_string();
+            }
+            SubscriberStatus::Pending => {
+                let last_sent = existing.confirmation_sent_at.unwrap_or(existing.created_at);
+                if now - last_sent < resend_after {
+                    return Ok(None);
+                }
+            }
+        }
+        existing.confirmation_sent_at = Some(now);
+        let subscriber = existing.clone();
+        write(SUBSCRIBERS_FILE, &subscribers)?;
+        return Ok(Some(subscriber));
+    }
+
+    let subscriber = Subscriber {
+        email: email.to_string(),
+        status: SubscriberStatus::Pending,
+        confirm_token: crate::utils::random_token(TOKEN_LEN),
+        unsubscribe_token: crate::utils::random_token(TOKEN_LEN),
+        lang: lang.to_string(),
+        created_at: now,
+        confirmed_at: None,
+        confirmation_sent_at: Some(now),
+    };
+    subscribers.push(subscriber.clone());
+    write(SUBSCRIBERS_FILE, &subscribers)?;
+    Ok(Some(subscriber))
+}
+
+/// Apply a change to the subscriber matching a predicate
+fn update(
+    matches: impl Fn(&Subscriber) -> bool,
+    change: impl FnOnce(&mut Subscriber),
+) -> Result<Option<Subscriber>> {
+    let _guard = lock().lock().unwrap();
+    let mut subscribers: Vec<Subscriber> = read(SUBSCRIBERS_FILE)?;
+    let updated = subscribers.iter_mut().find(|s| matches(s)).map(|subscriber| {
+        change(subscriber);
+        subscriber.clone()
+    });
+    if updated.is_some() {
+        write(SUBSCRIBERS_FILE, &subscribers)?;
+    }
+    Ok(updated)
+}
+
+pub fn confirm(token: &str) -> Result<Option<Subscriber>> {
+    update(
+        |subscriber| subscriber.status == SubscriberStatus::Pending && subscriber.confirm_token == token,
+        |subscriber| {
+            subscriber.status = SubscriberStatus::Confirmed;
+            subscriber.confirmed_at = Some(Utc::now());
+        },
+    )
+}
+
+pub fn by_unsubscribe_token(token: &str) -> Result<Option<Subscriber>> {
+    Ok(subscribers()?
+        .into_iter()
+        .find(|subscriber| subscriber.unsubscribe_token == token))
+}
+
+pub fn unsubscribe(token: &str) -> Result<Option<Subscriber>> {
+    update(
+        |subscriber| subscriber.unsubscribe_token == token,
+        |subscriber| subscriber.status = SubscriberStatus::Unsubscribed,
+    )
+}
+
+pub fn add_to_digest(slug: &str) -> Result<()> {
+    let _guard = lock().lock().unwrap();
+    let mut digest: PendingDigest = read(DIGEST_FILE)?;
+    if !digest.slugs.iter().any(|s| s == slug) {
+        digest.slugs.push(slug.to_string());
+    }
+    digest.since.get_or_insert_with(Utc::now);
+    write(DIGEST_FILE, &digest)
+}
+
+/// Take the pending digest if it has been collecting for at least `interval`
+pub fn take_digest_if_due(interval: chrono::Duration) -> Result<Option<PendingDigest>> {
+    let _guard = lock().lock().unwrap();
+    let digest: PendingDigest = read(DIGEST_FILE)?;
+    match digest.since {
+        Some(since) if Utc::now() - since >= interval && !digest.slugs.is_empty() => {
+            write(DIGEST_FILE, &PendingDigest::default())?;
+            Ok(Some(digest))
+        }
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,225 @@
+pub mod store;
+
+use crate::auth::jwt::Claims;
+use crate::i18n::Locale;
+use crate::markdown::Post;
+use crate::utils::escape_html;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, Json,
+    },
+    Extension,
+};
+use futures_util::stream::{self, Stream};
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::OnceLock;
+use store::{Notification, NotificationKind};
+use tokio::sync::broadcast;
+
+/// Response header that makes admin pages reload the badge and the list
+const REFRESH_NOTIFICATIONS: [(&str, &str); 1] = [("HX-Trigger", "notifications-changed")];
+/// Keeps nginx from buffering the event stream when no dedicated location is configured for it
+const NO_PROXY_BUFFERING: [(&str, &str); 1] = [("X-Accel-Buffering", "no")];
+
+/// Notifications as they are created, for the open event streams
+fn events() -> &'static broadcast::Sender<Notification> {
+    static EVENTS: OnceLock<broadcast::Sender<Notification>> = OnceLock::new();
+    EVENTS.get_or_init(|| broadcast::channel(64).0)
+}
+
+/// Tell the author of a post about activity on it
+pub fn notify(kind: NotificationKind, post: &Post, detail: &str) {
+    let notification = Notification {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        recipient: post.author_sub.clone(),
+        slug: post.slug.clone(),
+        post_title: post.title.clone(),
+        detail: detail.to_string(),
+        created_at: chrono::Utc::now(),
+        read_by: Default::default(),
+    };
+    if let Err(e) = store::add(&notification) {
+        tracing::error!("Failed to store notification about {}: {}", post.slug, e);
+        return;
+    }
+    // Nobody listening is fine, the notification waits unread
+    let _ = events().send(notification);
+}
+
+/// Called by `markdown::writer` when a post is created
+pub fn post_published(post: &Post) {
+    notify(NotificationKind::PostPublished, post, "");
+}
+
+/// Called by `markdown::writer` when a post is deleted
+pub fn post_deleted(slug: &str) {
+    if let Err(e) = store::remove_for_post(slug) {
+        tracing::error!("Failed to remove notifications about {}: {}", slug, e);
+    }
+}
+
+fn to_sse(notification: &Notification) -> Event {
+    Event::default()
+        .event("notification")
+        .json_data(notification)
+        .unwrap_or_else(|_| Event::default().comment("serialization failed"))
+}
+
+/// `/admin/notifications/stream`: server-sent events for each new notification addressed to the author
+///
+/// Admin pages listen with the HTMX SSE extension and reload their badge on each `notification` event.
+pub async fn stream(
+    Extension(claims): Extension<Claims>,
+) -> (
+    [(&'static str, &'static str); 1],
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+) {
+    let receiver = events().subscribe();
+    let events = stream::unfold((receiver, claims.sub), |(mut receiver, sub)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) if notification.is_for(&sub) => {
+                    return Some((Ok(to_sse(&notification)), (receiver, sub)));
+                }
+                Ok(_) => {}
+                // Missed notifications are in the store; the next one refreshes the badge
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    (NO_PROXY_BUFFERING, Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn store_error(e: anyhow::Error) -> StatusCode {
+    tracing::error!("Failed to read notifications: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// `/admin/notifications/badge`: number of unread notifications, empty when there are none
+pub async fn badge(Extension(claims): Extension<Claims>) -> Result<Html<String>, StatusCode> {
+    let unread = store::unread_count(&claims.sub).map_err(store_error)?;
+    if unread == 0 {
+        return Ok(Html(String::new()));
+    }
+    Ok(Html(format!(
+        r#"<span class="notification-count">{}</span>"#,
+        if unread > 99 { "99+".to_string() } else { unread.to_string() }
+    )))
+}
+
+/// `/admin/notifications/list`: the author's notifications, newest first
+pub async fn list(Extension(claims): Extension<Claims>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let notifications = store::for_author(&claims.sub).map_err(store_error)?;
+    let unread = notifications
+        .iter()
+        .filter(|notification| !notification.is_read_by(&claims.sub))
+        .count();
+    Ok(Json(json!({
+        "success": true,
+        "unread": unread,
+        "notifications": notifications
+            .iter()
+            .map(|notification| json!({
+                "id": notification.id,
+                "kind": notification.kind,
+                "slug": notification.slug,
+                "post_title": notification.post_title,
+                "detail": notification.detail,
+                "created_at": notification.created_at,
+                "read": notification.is_read_by(&claims.sub),
+            }))
+            .collect::<Vec<_>>(),
+    })))
+}
+
+/// What happened, and the page where the author can act on it
+fn describe(notification: &Notification, locale: &Locale) -> (String, String) {
+    let message = locale
+        .t(&format!("notifications.{}", notification.kind.name()))
+        .replace("{title}", &escape_html(&notification.post_title))
+        .replace("{detail}", &escape_html(&notification.detail));
+    let link = match notification.kind {
+        NotificationKind::CommentPending => "/admin/comments".to_string(),
+        NotificationKind::PostPublished | NotificationKind::MentionReceived => {
+            format!("/posts/{}", notification.slug)
+        }
+    };
+    (message, link)
+}
+
+/// `/admin/notifications/list/html`: list items for the notifications page
+pub async fn list_html(
+    Extension(claims): Extension<Claims>,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    let notifications = store::for_author(&claims.sub).map_err(store_error)?;
+    if notifications.is_empty() {
+        return Ok(Html(format!(
+            r#"<li class="notifications-empty">{}</li>"#,
+            locale.t("notifications.empty")
+        )));
+    }
+
+    let items: String = notifications
+        .iter()
+        .map(|notification| {
+            let (message, link) = describe(notification, &locale);
+            let read = notification.is_read_by(&claims.sub);
+            let button = if read {
+                String::new()
+            } else {
+                format!(
+                    r#"<button class="btn btn-secondary" hx-post="/admin/notifications/{}/read" hx-swap="none">{}</button>"#,
+                    notification.id,
+                    locale.t("notifications.mark_read")
+                )
+            };
+            format!(
+                r#"<li class="notification{}"><a href="{}">{}</a> <span class="notification-date">{}</span> {}</li>"#,
+                if read { "" } else { " unread" },
+                link,
+                message,
+                locale.format_date(&notification.created_at),
+                button
+            )
+        })
+        .collect();
+    Ok(Html(items))
+}
+
+pub async fn serve_notifications_page(locale: Locale) -> Result<Html<String>, StatusCode> {
+    let template = std::fs::read_to_string("../frontend/templates/admin/notifications.html")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Html(locale.localize(&template)))
+}
+
+type ReadResponse = ([(&'static str, &'static str); 1], Json<serde_json::Value>);
+
+fn mark_read(sub: &str, id: Option<&str>) -> Result<ReadResponse, StatusCode> {
+    store::mark_read(sub, id).map_err(|e| {
+        tracing::error!("Failed to mark notifications read: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((REFRESH_NOTIFICATIONS, Json(json!({ "success": true }))))
+}
+
+/// `POST /admin/notifications/{id}/read`
+pub async fn mark_one_read(
+    Path(id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Result<ReadResponse, StatusCode> {
+    mark_read(&claims.sub, Some(&id))
+}
+
+/// `POST /admin/notifications/read`: mark everything as seen
+pub async fn mark_all_read(
+    Extension(claims): Extension<Claims>,
+) -> Result<ReadResponse, StatusCode> {
+    mark_read(&claims.sub, None)
+}
@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const NOTIFICATIONS_FILE: &str = "notifications.json";
+/// Older notifications are dropped once there are more than this
+const MAX_KEPT: usize = 500;
+
+/// Activity an author is told about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A reader comment is waiting in the moderation queue
+    CommentPending,
+    PostPublished,
+    /// A verified webmention came in
+    MentionReceived,
+}
+
+impl NotificationKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::CommentPending => "comment_pending",
+            Self::PostPublished => "post_published",
+            Self::MentionReceived => "mention_received",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub kind: NotificationKind,
+    /// Keycloak subject of the post author, none for posts written before authors were recorded,
+    /// whose notifications go to every author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    pub slug: String,
+    pub post_title: String,
+    /// Who commented or where the mention comes from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+    pub created_at: DateTime<Utc>,
+    /// Subjects of the authors who have seen it
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub read_by: BTreeSet<String>,
+}
+
+impl Notification {
+    pub fn is_for(&self, sub: &str) -> bool {
+        self.recipient.as_deref().is_none_or(|recipient| recipient == sub)
+    }
+
+    pub fn is_read_by(&self, sub: &str) -> bool {
+        self.read_by.contains(sub)
+    }
+}
+
+/// Read on every badge refresh and stream tick, so kept in memory; changes are written through
+fn cache() -> &'static Mutex<Option<Vec<Notification>>> {
+    static CACHE: OnceLock<Mutex<Option<Vec<Notification>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(None))
+}
+
+fn read() -> Result<Vec<Notification>> {
+    let path = Path::new(NOTIFICATIONS_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read notifications.json")?;
+    serde_json::from_str(&content).context("Failed to parse notifications.json")
+}
+
+fn write(notifications: &[Notification]) -> Result<()> {
+    let content = serde_json::to_string_pretty(notifications)
+        .context("Failed to serialize notifications")?;
+    std::fs::write(NOTIFICATIONS_FILE, content).context("Failed to write notifications.json")
+}
+
+/// Run `f` on the notifications, saving them when it reports a change
+fn with<R>(f: impl FnOnce(&mut Vec<Notification>) -> (R, bool)) -> Result<R> {
+    let mut cache = cache().lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.is_none() {
+        *cache = Some(read()?);
+    }
+    let notifications = cache.as_mut().expect("notifications are loaded");
+    let (result, changed) = f(notifications);
+    if changed {
+        if let Err(e) = write(notifications) {
+            // Reload from disk next time rather than keep serving a change that was not saved
+            *cache = None;
+            return Err(e);
+        }
+    }
+    Ok(result)
+}
+
+pub fn add(notification: &Notification) -> Result<()> {
+    with(|notifications| {
+        notifications.push(notification.clone());
+        if notifications.len() > MAX_KEPT {
+            notifications.sort_by_key(|notification| notification.created_at);
+            let excess = notifications.len() - MAX_KEPT;
+            notifications.drain(..excess);
+        }
+        ((), true)
+    })
+}
+
+/// Notifications addressed to an author, newest first
+pub fn for_author(sub: &str) -> Result<Vec<Notification>> {
+    let mut notifications: Vec<Notification> = with(|notifications| {
+        let mine = notifications
+            .iter()
+            .filter(|notification| notification.is_for(sub))
+            .cloned()
+            .collect();
+        (mine, false)
+    })?;
+    notifications.sort_by_key(|notification| std::cmp::Reverse(notification.created_at));
+    Ok(notifications)
+}
+
+pub fn unread_count(sub: &str) -> Result<usize> {
+    with(|notifications| {
+        let count = notifications
+            .iter()
+            .filter(|notification| notification.is_for(sub) && !notification.is_read_by(sub))
+            .count();
+        (count, false)
+    })
+}
+
+/// Mark one notification, or all of them when `id` is none, as seen by an author
+pub fn mark_read(sub: &str, id: Option<&str>) -> Result<bool> {
+    with(|notifications| {
+        let mut changed = false;
+        for notification in notifications
+            .iter_mut()
+            .filter(|notification| notification.is_for(sub))
+            .filter(|notification| id.is_none_or(|id| notification.id == id))
+        {
+            changed |= notification.read_by.insert(sub.to_string());
+        }
+        (changed, changed)
+    })
+}
+
+/// Remove the notifications about a post
+pub fn remove_for_post(slug: &str) -> Result<()> {
+    with(|notifications| {
+        let before = notifications.len();
+        notifications.retain(|notification| notification.slug != slug);
+        ((), notifications.len() != before)
+    })
+}
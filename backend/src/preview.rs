@@ -0,0 +1,243 @@
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, Json,
+    },
+};
+use dashmap::DashMap;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// How long the author has to stop typing before a draft is re-rendered
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Drafts nobody has touched or watched for this long are dropped
+const DRAFT_TTL: Duration = Duration::from_secs(60 * 60);
+/// How often abandoned drafts are looked for
+const PRUNE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Update pushed to every viewer of a draft
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PreviewEvent {
+    /// The complete rendered draft
+    Full { html: String },
+    /// Replace `remove` lines starting at line `from` with `insert`
+    Patch {
+        from: usize,
+        remove: usize,
+        insert: Vec<String>,
+    },
+}
+
+struct Draft {
+    content: Mutex<String>,
+    // Rendered HTML split into lines, as last sent to viewers
+    lines: Mutex<Vec<String>>,
+    generation: AtomicU64,
+    touched: Mutex<Instant>,
+    events: broadcast::Sender<PreviewEvent>,
+}
+
+impl Draft {
+    fn new() -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            content: Mutex::new(String::new()),
+            lines: Mutex::new(Vec::new()),
+            generation: AtomicU64::new(0),
+            touched: Mutex::new(Instant::now()),
+            events,
+        }
+    }
+
+    fn html(&self) -> String {
+        self.lines.lock().unwrap_or_else(|e| e.into_inner()).concat()
+    }
+
+    fn touch(&self) {
+        *self.touched.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn is_stale(&self) -> bool {
+        self.events.receiver_count() == 0
+            && self.touched.lock().unwrap_or_else(|e| e.into_inner()).elapsed() > DRAFT_TTL
+    }
+
+    /// Render the current content and broadcast the changed lines
+    fn publish(&self) {
+        let content = self.content.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let html = crate::markdown::reader::markdown_to_html(&content);
+        let new_lines: Vec<String> = html.split_inclusive('\n').map(String::from).collect();
+
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(patch) = diff_lines(&lines, &new_lines) {
+            *lines = new_lines;
+            // Nobody listening is not an error, the draft is still kept up to date
+            let _ = self.events.send(patch);
+        }
+    }
+}
+
+fn drafts() -> &'static DashMap<String, Arc<Draft>> {
+    static DRAFTS: OnceLock<DashMap<String, Arc<Draft>>> = OnceLock::new();
+    DRAFTS.get_or_init(DashMap::new)
+}
+
+fn draft(draft_id: &str) -> Arc<Draft> {
+    drafts()
+        .entry(draft_id.to_string())
+        .or_insert_with(|| Arc::new(Draft::new()))
+        .clone()
+}
+
+/// Drafts are only created by their author; viewers of an unknown draft get nothing
+fn existing_draft(draft_id: &str) -> Option<Arc<Draft>> {
+    drafts().get(draft_id).map(|draft| draft.clone())
+}
+
+fn prune_stale_drafts() {
+    drafts().retain(|_, draft| !draft.is_stale());
+}
+
+/// Drop abandoned drafts in the background
+pub fn spawn_pruner() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            prune_stale_drafts();
+        }
+    });
+}
+
+fn is_valid_draft_id(draft_id: &str) -> bool {
+    !draft_id.is_empty()
+        && draft_id.len() <= 64
+        && draft_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Smallest single-range patch turning `old` into `new`, if they differ
+fn diff_lines(old: &[String], new: &[String]) -> Option<PreviewEvent> {
+    if old == new {
+        return None;
+    }
+
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    Some(PreviewEvent::Patch {
+        from: prefix,
+        remove: old.len() - prefix - suffix,
+        insert: new[prefix..new.len() - suffix].to_vec(),
+    })
+}
+
+fn to_sse(event: &PreviewEvent) -> Event {
+    let name = match event {
+        PreviewEvent::Full { .. } => "full",
+        PreviewEvent::Patch { .. } => "patch",
+    };
+    Event::default()
+        .event(name)
+        .json_data(event)
+        .unwrap_or_else(|_| Event::default().comment("serialization failed"))
+}
+
+#[derive(Deserialize)]
+pub struct DraftUpdate {
+    content: String,
+}
+
+/// Receive the latest draft content from the editor; rendering is debounced
+pub async fn update_draft(
+    Path(draft_id): Path<String>,
+    Json(payload): Json<DraftUpdate>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !is_valid_draft_id(&draft_id) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let draft = draft(&draft_id);
+    *draft.content.lock().unwrap_or_else(|e| e.into_inner()) = payload.content;
+    draft.touch();
+    let generation = draft.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+    tokio::spawn(async move {
+        tokio::time::sleep(DEBOUNCE).await;
+        // A newer update arrived while we waited; its own task will render
+        if draft.generation.load(Ordering::SeqCst) == generation {
+            draft.publish();
+        }
+    });
+
+    Ok(Json(json!({ "success": true })))
+}
+
+/// Stream rendered fragments of a draft as server-sent events
+pub async fn stream_draft(
+    Path(draft_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    if !is_valid_draft_id(&draft_id) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let draft = existing_draft(&draft_id).ok_or(StatusCode::NOT_FOUND)?;
+    draft.touch();
+    let receiver = draft.events.subscribe();
+    let initial = PreviewEvent::Full { html: draft.html() };
+
+    let events = stream::unfold(
+        (Some(initial), receiver, draft),
+        |(pending, mut receiver, draft)| async move {
+            if let Some(event) = pending {
+                return Some((Ok(to_sse(&event)), (None, receiver, draft)));
+            }
+
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                // The viewer fell behind; resynchronise with the whole document
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    PreviewEvent::Full { html: draft.html() }
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            Some((Ok(to_sse(&event)), (None, receiver, draft)))
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Read-only page for reviewers following a draft as it is written
+pub async fn watch_draft(Path(draft_id): Path<String>) -> Result<Html<String>, StatusCode> {
+    if !is_valid_draft_id(&draft_id) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let draft = existing_draft(&draft_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    match std::fs::read_to_string("../frontend/templates/preview.html") {
+        Ok(template) => Ok(Html(
+            template
+                .replace("{{ draft_id }}", &draft_id)
+                .replace("{{ content | safe }}", &draft.html()),
+        )),
+        Err(_) => Ok(Html(
+            "<h1>Error</h1><p>Could not load preview template.</p>".to_string(),
+        )),
+    }
+}
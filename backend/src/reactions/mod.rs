@@ -0,0 +1,227 @@
+pub mod store;
+
+use crate::i18n::Locale;
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Cookie identifying a reader who is not logged in
+const REACTOR_COOKIE: &str = "reactor";
+/// Reactions one address may toggle within the spam module's rate window
+const TOGGLE_RATE_LIMIT: usize = 30;
+
+/// Reactions readers can leave on a post, in display order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReactionKind {
+    Like,
+    Love,
+    Insightful,
+    Celebrate,
+}
+
+impl ReactionKind {
+    pub const ALL: [ReactionKind; 4] = [Self::Like, Self::Love, Self::Insightful, Self::Celebrate];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Like => "like",
+            Self::Love => "love",
+            Self::Insightful => "insightful",
+            Self::Celebrate => "celebrate",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Self::Like => "👍",
+            Self::Love => "❤️",
+            Self::Insightful => "💡",
+            Self::Celebrate => "🎉",
+        }
+    }
+}
+
+/// Called by `markdown::writer` when a post is deleted
+pub fn post_deleted(slug: &str) {
+    if let Err(e) = store::remove_post(slug) {
+        tracing::error!("Failed to remove reactions of {}: {}", slug, e);
+    }
+}
+
+/// HTMX attributes that swap the counts for buttons, removed from static exports
+pub fn fragment_attributes(slug: &str) -> String {
+    format!(r#" hx-get="/posts/{}/reactions" hx-trigger="load" hx-swap="outerHTML""#, slug)
+}
+
+/// Reaction counts under a post, replaced by the buttons of the reader on load
+pub fn section(slug: &str, locale: &Locale) -> String {
+    format!(
+        r#"<div class="reactions"{}>{}</div>"#,
+        fragment_attributes(slug),
+        counts_html(&store::counts(slug), locale)
+    )
+}
+
+/// Read-only counts of every reaction
+fn counts_html(counts: &BTreeMap<ReactionKind, usize>, locale: &Locale) -> String {
+    ReactionKind::ALL
+        .into_iter()
+        .map(|kind| {
+            format!(
+                r#"<span class="reaction" title="{}"><span aria-hidden="true">{}</span> <span class="reaction-count">{}</span></span>"#,
+                locale.t(&format!("reactions.{}", kind.name())),
+                kind.emoji(),
+                counts.get(&kind).copied().unwrap_or(0)
+            )
+        })
+        .collect()
+}
+
+/// Counts of the reactions a post received, for its card; empty when it has none
+pub fn summary(counts: Option<&BTreeMap<ReactionKind, usize>>, locale: &Locale) -> String {
+    let Some(counts) = counts.filter(|counts| !counts.is_empty()) else {
+        return String::new();
+    };
+    let items: String = counts
+        .iter()
+        .map(|(kind, count)| {
+            format!(
+                r#"<span class="reaction" title="{}"><span aria-hidden="true">{}</span> {}</span>"#,
+                locale.t(&format!("reactions.{}", kind.name())),
+                kind.emoji(),
+                count
+            )
+        })
+        .collect();
+    format!(r#"<div class="post-card-reactions">{}</div>"#, items)
+}
+
+/// One toggle button per reaction, pressed for those the reader left
+fn buttons_html(slug: &str, reacted: &BTreeSet<ReactionKind>, locale: &Locale) -> String {
+    let counts = store::counts(slug);
+    let buttons: String = ReactionKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let pressed = reacted.contains(&kind);
+            let count = counts.get(&kind).copied().unwrap_or(0);
+            let label = locale.t(&format!("reactions.{}", kind.name()));
+            format!(
+                r#"<button type="button" class="reaction{}" hx-post="/posts/{}/reactions/{}" hx-target="closest .reactions" hx-swap="outerHTML" aria-pressed="{}" aria-label="{} ({})" title="{}"><span aria-hidden="true">{}</span> <span class="reaction-count">{}</span></button>"#,
+                if pressed { " active" } else { "" },
+                slug,
+                kind.name(),
+                pressed,
+                label,
+                count,
+                label,
+                kind.emoji(),
+                count
+            )
+        })
+        .collect();
+    format!(r#"<div class="reactions">{}</div>"#, buttons)
+}
+
+/// Reader id as stored, keyed with the server's salt so ids cannot be matched to subjects or cookies
+fn hashed(kind: &str, id: &str) -> anyhow::Result<String> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, store::salt()?);
+    let tag = hmac::sign(&key, format!("{}:{}", kind, id).as_bytes());
+    Ok(tag.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn reactor_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::COOKIE)
+        .and_then(|h| h.to_str().ok())?
+        .split(';')
+        .map(|c| c.trim())
+        .find_map(|c| c.strip_prefix(&format!("{}=", REACTOR_COOKIE)))
+        .filter(|value| uuid::Uuid::parse_str(value).is_ok())
+        .map(String::from)
+}
+
+/// Who is reacting, as stored: the hashed `sub` of a logged-in reader or the hashed anonymous cookie
+async fn reactor(headers: &HeaderMap) -> Result<Option<String>, StatusCode> {
+    let reactor = match crate::auth::optional_claims(headers).await {
+        Some(claims) => hashed("sub", &claims.sub).map(Some),
+        None => reactor_cookie(headers)
+            .map(|cookie| hashed("anon", &cookie))
+            .transpose(),
+    };
+    reactor.map_err(reactor_error)
+}
+
+fn reactor_error(e: anyhow::Error) -> StatusCode {
+    tracing::error!("Failed to identify reader: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// `GET /posts/{slug}/reactions`: the buttons, showing what the reader already reacted with
+pub async fn fragment(
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+    locale: Locale,
+) -> Result<Html<String>, StatusCode> {
+    let post = crate::markdown::reader::read_post(&slug).map_err(|_| StatusCode::NOT_FOUND)?;
+    let reacted = match reactor(&headers).await? {
+        Some(reactor) => store::reacted(&post.slug, &reactor),
+        None => BTreeSet::new(),
+    };
+    Ok(Html(buttons_html(&post.slug, &reacted, &locale)))
+}
+
+/// `POST /posts/{slug}/reactions/{kind}`: add or take back a reaction
+///
+/// Readers who are not logged in get a random cookie the first time, so they can react only once.
+/// Clearing the cookie gives a new one, so toggles are also rate limited per address.
+pub async fn toggle(
+    Path((slug, kind)): Path<(String, String)>,
+    headers: HeaderMap,
+    locale: Locale,
+) -> Result<Response, StatusCode> {
+    if crate::spam::rate_limited("reactions", &headers, TOGGLE_RATE_LIMIT) {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    let post = crate::markdown::reader::read_post(&slug).map_err(|_| StatusCode::NOT_FOUND)?;
+    let kind = ReactionKind::from_name(&kind).ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut new_cookie = None;
+    let reactor = match reactor(&headers).await? {
+        Some(reactor) => reactor,
+        None => {
+            let cookie = uuid::Uuid::new_v4().to_string();
+            let reactor = hashed("anon", &cookie).map_err(reactor_error)?;
+            new_cookie = Some(cookie);
+            reactor
+        }
+    };
+
+    store::toggle(&post.slug, kind, &reactor).map_err(|e| {
+        tracing::error!("Failed to store reaction on {}: {}", post.slug, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut response = Html(buttons_html(&post.slug, &store::reacted(&post.slug, &reactor), &locale)).into_response();
+    if let Some(cookie) = new_cookie {
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+            REACTOR_COOKIE,
+            cookie,
+            365 * 24 * 60 * 60
+        );
+        if let Ok(value) = cookie.parse() {
+            response.headers_mut().insert(header::SET_COOKIE, value);
+        }
+    }
+    Ok(response)
+}
@@ -0,0 +1,164 @@
+use super::ReactionKind;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const REACTIONS_FILE: &str = "reactions.json";
+/// Secret mixed into the stored reader ids
+const SALT_FILE: &str = "reactions_salt";
+const SALT_BYTES: usize = 32;
+
+/// Hashed ids of the readers who left each reaction, by post slug
+type Reactions = BTreeMap<String, BTreeMap<ReactionKind, BTreeSet<String>>>;
+
+/// Reactions are read on every listing, so they are kept in memory once loaded
+fn cache() -> &'static Mutex<Option<Reactions>> {
+    static CACHE: OnceLock<Mutex<Option<Reactions>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(None))
+}
+
+/// Salt for reader ids, generated and saved on first use so readers keep their id across restarts
+///
+/// Loading runs once, inside the `OnceLock`, so concurrent first calls cannot each save a salt of
+/// their own. A salt that cannot be read or saved stays an error until the next restart.
+pub fn salt() -> Result<&'static [u8]> {
+    static SALT: OnceLock<Result<Vec<u8>, String>> = OnceLock::new();
+    SALT.get_or_init(|| load_or_create_salt().map_err(|e| format!("{:#}", e)))
+        .as_deref()
+        .map_err(|e| anyhow!("{}", e))
+}
+
+fn load_or_create_salt() -> Result<Vec<u8>> {
+    let path = Path::new(SALT_FILE);
+    if path.exists() {
+        let salt = std::fs::read(path).context("Failed to read reactions_salt")?;
+        if salt.len() < SALT_BYTES {
+            bail!("reactions_salt is shorter than {} bytes", SALT_BYTES);
+        }
+        return Ok(salt);
+    }
+    let salt = rand::random::<[u8; SALT_BYTES]>().to_vec();
+    std::fs::write(path, &salt).context("Failed to save reactions_salt")?;
+    Ok(salt)
+}
+
+fn read() -> Result<Reactions> {
+    let path = Path::new(REACTIONS_FILE);
+    if !path.exists() {
+        return Ok(Reactions::new());
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read reactions.json")?;
+    serde_json::from_str(&content).context("Failed to parse reactions.json")
+}
+
+fn write(reactions: &Reactions) -> Result<()> {
+    let content = serde_json::to_string(reactions).context("Failed to serialize reactions")?;
+    std::fs::write(REACTIONS_FILE, content).context("Failed to write reactions.json")
+}
+
+/// Run `f` on the loaded reactions, writing them back when it reports a change
+fn with<R>(f: impl FnOnce(&mut Reactions) -> (R, bool)) -> Result<R> {
+    let mut cache = cache().lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.is_none() {
+        *cache = Some(read()?);
+    }
+    let reactions = cache.as_mut().expect("reactions are loaded");
+    let (result, changed) = f(reactions);
+    if changed {
+        if let Err(e) = write(reactions) {
+            // Reload from disk next time, so a reaction that was not saved is not shown either
+            *cache = None;
+            return Err(e);
+        }
+    }
+    Ok(result)
+}
+
+fn count(kinds: &BTreeMap<ReactionKind, BTreeSet<String>>) -> BTreeMap<ReactionKind, usize> {
+    kinds
+        .iter()
+        .filter(|(_, reactors)| !reactors.is_empty())
+        .map(|(kind, reactors)| (*kind, reactors.len()))
+        .collect()
+}
+
+/// Number of each reaction on a post
+pub fn counts(slug: &str) -> BTreeMap<ReactionKind, usize> {
+    with(|reactions| (reactions.get(slug).map(count).unwrap_or_default(), false)).unwrap_or_default()
+}
+
+/// Number of each reaction on every post, for listings
+pub fn all_counts() -> HashMap<String, BTreeMap<ReactionKind, usize>> {
+    with(|reactions| {
+        let counts = reactions
+            .iter()
+            .map(|(slug, kinds)| (slug.clone(), count(kinds)))
+            .collect();
+        (counts, false)
+    })
+    .unwrap_or_default()
+}
+
+/// Reactions a reader left on a post
+pub fn reacted(slug: &str, reactor: &str) -> BTreeSet<ReactionKind> {
+    with(|reactions| {
+        let kinds = reactions.get(slug).map_or_else(BTreeSet::new, |kinds| {
+            kinds
+                .iter()
+                .filter(|(_, reactors)| reactors.contains(reactor))
+                .map(|(kind, _)| *kind)
+                .collect()
+        });
+        (kinds, false)
+    })
+    .unwrap_or_default()
+}
+
+/// Add the reaction, or take it back if the reader already left it; returns whether it is set now
+pub fn toggle(slug: &str, kind: ReactionKind, reactor: &str) -> Result<bool> {
+    with(|reactions| {
+        let kinds = reactions.entry(slug.to_string()).or_default();
+        let reactors = kinds.entry(kind).or_default();
+        let set = if reactors.remove(reactor) {
+            false
+        } else {
+            reactors.insert(reactor.to_string());
+            true
+        };
+        // Keep the file free of reactions nobody left
+        kinds.retain(|_, reactors| !reactors.is_empty());
+        if kinds.is_empty() {
+            reactions.remove(slug);
+        }
+        (set, true)
+    })
+}
+
+pub fn remove_post(slug: &str) -> Result<()> {
+    with(|reactions| ((), reactions.remove(slug).is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScratchDir;
+
+    #[tokio::test]
+    async fn a_reaction_that_was_not_saved_is_not_kept() {
+        let _dir = ScratchDir::new().await;
+        *cache().lock().unwrap() = None;
+        assert!(toggle("hello", ReactionKind::Like, "reader-a").unwrap());
+        let saved = std::fs::read(REACTIONS_FILE).unwrap();
+
+        // A directory in place of the file makes the next write fail
+        std::fs::remove_file(REACTIONS_FILE).unwrap();
+        std::fs::create_dir(REACTIONS_FILE).unwrap();
+        assert!(toggle("hello", ReactionKind::Like, "reader-b").is_err());
+
+        std::fs::remove_dir(REACTIONS_FILE).unwrap();
+        std::fs::write(REACTIONS_FILE, saved).unwrap();
+        assert_eq!(counts("hello").get(&ReactionKind::Like), Some(&1));
+        assert!(reacted("hello", "reader-b").is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::markdown::Post;
+use crate::utils::escape_html;
+use chrono::{DateTime, Utc};
+
+const DESCRIPTION_CHARS: usize = 200;
+
+/// Sharing metadata for a post page: OpenGraph, Twitter Card and canonical URL
+#[derive(Debug, Clone)]
+pub struct PageMeta {
+    pub title: String,
+    pub description: String,
+    pub image: Option<String>,
+    /// Description of the image, known when it is the post's cover
+    pub image_alt: Option<String>,
+    pub author: String,
+    pub published: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    pub canonical_url: String,
+    pub locale: String,
+    pub tags: Vec<String>,
+}
+
+/// Turn a site-relative path into an absolute URL
+pub fn absolute_url(url: &str) -> String {
+    if url.starts_with('/') && !url.starts_with("//") {
+        format!("{}{}", crate::utils::public_base_url(), url)
+    } else {
+        url.to_string()
+    }
+}
+
+/// OpenGraph locale for a post language, e.g. `fr` -> `fr_FR`
+fn og_locale(lang: &str) -> String {
+    match lang {
+        "en" => "en_US".to_string(),
+        "fr" => "fr_FR".to_string(),
+        other => other.to_string(),
+    }
+}
+
+impl PageMeta {
+    /// Metadata generated from the post, with the author's overrides applied
+    pub fn for_post(post: &Post) -> Self {
+        let description = post
+            .seo
+            .description
+            .clone()
+            .unwrap_or_else(|| crate::markdown::reader::excerpt(&post.content, DESCRIPTION_CHARS));
+        // An explicit sharing image wins over the cover, which wins over the site default
+        let cover = post.cover.as_ref().filter(|_| post.seo.image.is_none());
+        let image = post
+            .seo
+            .image
+            .clone()
+            .or_else(|| cover.map(|cover| cover.url.clone()))
+            .or_else(|| std::env::var("BLOG_DEFAULT_OG_IMAGE").ok())
+            .filter(|image| !image.is_empty())
+            .map(|image| absolute_url(&image));
+        let image_alt = cover
+            .map(|cover| cover.alt.clone())
+            .filter(|alt| !alt.is_empty());
+
+        Self {
+            title: post.seo.title.clone().unwrap_or_else(|| post.title.clone()),
+            description,
+            image,
+            image_alt,
+            author: post.author.clone(),
+            published: post.created_at,
+            modified: post.updated_at,
+            canonical_url: crate::feeds::post_url(post),
+            locale: og_locale(&post.lang),
+            tags: post.tags.clone(),
+        }
+    }
+
+    /// Render the `<head>` tags
+    pub fn render(&self) -> String {
+        let mut tags: Vec<String> = Vec::new();
+        let meta_name = |name: &str, content: &str| {
+            format!(
+                r#"<meta name="{}" content="{}">"#,
+                name,
+                escape_html(content)
+            )
+        };
+        let meta_property = |property: &str, content: &str| {
+            format!(
+                r#"<meta property="{}" content="{}">"#,
+                property,
+                escape_html(content)
+            )
+        };
+
+        tags.push(format!(
+            r#"<link rel="canonical" href="{}">"#,
+            escape_html(&self.canonical_url)
+        ));
+        tags.push(meta_name("description", &self.description));
+        tags.push(meta_name("author", &self.author));
+
+        tags.push(meta_property("og:type", "article"));
+        tags.push(meta_property("og:site_name", &crate::utils::site_title()));
+        tags.push(meta_property("og:title", &self.title));
+        tags.push(meta_property("og:description", &self.description));
+        tags.push(meta_property("og:url", &self.canonical_url));
+        tags.push(meta_property("og:locale", &self.locale));
+        tags.push(meta_property("article:author", &self.author));
+        tags.push(meta_property(
+            "article:published_time",
+            &self.published.to_rfc3339(),
+        ));
+        tags.push(meta_property(
+            "article:modified_time",
+            &self.modified.to_rfc3339(),
+        ));
+        for tag in &self.tags {
+            tags.push(meta_property("article:tag", tag));
+        }
+
+        let card = if self.image.is_some() {
+            "summary_large_image"
+        } else {
+            "summary"
+        };
+        tags.push(meta_name("twitter:card", card));
+        tags.push(meta_name("twitter:title", &self.title));
+        tags.push(meta_name("twitter:description", &self.description));
+
+        if let Some(image) = &self.image {
+            tags.push(meta_property("og:image", image));
+            tags.push(meta_name("twitter:image", image));
+        }
+        if let Some(alt) = &self.image_alt {
+            tags.push(meta_property("og:image:alt", alt));
+            tags.push(meta_name("twitter:image:alt", alt));
+        }
+
+        tags.join("\n    ")
+    }
+}
@@ -0,0 +1,4 @@
+pub mod meta;
+pub mod robots;
+pub mod sitemap;
+pub mod structured_data;
@@ -0,0 +1,34 @@
+use axum::http::header;
+use axum::response::IntoResponse;
+
+/// Paths that are never useful to crawlers
+const ALWAYS_DISALLOWED: &[&str] = &["/admin/", "/auth/"];
+
+/// Build robots.txt; BLOG_ROBOTS_DISALLOW adds comma-separated paths and
+/// BLOG_ROBOTS_DISALLOW_ALL=true hides the whole site (e.g. for staging)
+pub fn render() -> String {
+    let mut robots = String::from("User-agent: *\n");
+
+    let disallow_all = std::env::var("BLOG_ROBOTS_DISALLOW_ALL")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if disallow_all {
+        robots.push_str("Disallow: /\n");
+    } else {
+        let extra = std::env::var("BLOG_ROBOTS_DISALLOW").unwrap_or_default();
+        let configured = extra.split(',').map(str::trim).filter(|p| !p.is_empty());
+        for path in ALWAYS_DISALLOWED.iter().copied().chain(configured) {
+            robots.push_str(&format!("Disallow: {}\n", path));
+        }
+    }
+
+    robots.push_str(&format!(
+        "\nSitemap: {}/sitemap.xml\n",
+        crate::utils::public_base_url()
+    ));
+    robots
+}
+
+pub async fn robots_txt() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], render())
+}
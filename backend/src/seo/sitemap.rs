@@ -0,0 +1,121 @@
+use crate::feeds::{conditional_response, last_modified, post_url, scoped_posts, FeedScope};
+use crate::markdown::Post;
+use crate::utils::escape_html;
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+
+/// Limit on URLs per sitemap file set by the sitemaps.org protocol
+const MAX_URLS_PER_SITEMAP: usize = 50_000;
+const CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+/// A single `<url>` entry
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: DateTime<Utc>,
+}
+
+/// Every URL to list: the home page followed by each published post
+pub fn urls(posts: &[Post]) -> Vec<SitemapUrl> {
+    let home = SitemapUrl {
+        loc: format!("{}/", crate::utils::public_base_url()),
+        lastmod: last_modified(posts),
+    };
+
+    std::iter::once(home)
+        .chain(posts.iter().map(|post| SitemapUrl {
+            loc: post_url(post),
+            lastmod: post.updated_at,
+        }))
+        .collect()
+}
+
+pub fn render_urlset(urls: &[SitemapUrl]) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for url in urls {
+        xml.push_str(&format!(
+            "<url><loc>{}</loc><lastmod>{}</lastmod></url>",
+            escape_html(&url.loc),
+            url.lastmod.to_rfc3339()
+        ));
+    }
+    xml.push_str("</urlset>");
+    xml
+}
+
+/// Sitemap index pointing at `/sitemaps/{n}.xml` for each chunk of URLs
+pub fn render_index(chunks: &[&[SitemapUrl]]) -> String {
+    let base_url = crate::utils::public_base_url();
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for (index, chunk) in chunks.iter().enumerate() {
+        let lastmod = chunk
+            .iter()
+            .map(|url| url.lastmod)
+            .max()
+            .unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+        xml.push_str(&format!(
+            "<sitemap><loc>{}/sitemaps/{}.xml</loc><lastmod>{}</lastmod></sitemap>",
+            escape_html(&base_url),
+            index + 1,
+            lastmod.to_rfc3339()
+        ));
+    }
+    xml.push_str("</sitemapindex>");
+    xml
+}
+
+/// `/sitemap.xml`: a plain urlset, or a sitemap index once there are too many URLs
+pub async fn sitemap_xml(headers: HeaderMap) -> Result<Response, StatusCode> {
+    let posts = scoped_posts(&FeedScope::All).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let urls = urls(&posts);
+
+    let body = if urls.len() > MAX_URLS_PER_SITEMAP {
+        let chunks: Vec<&[SitemapUrl]> = urls.chunks(MAX_URLS_PER_SITEMAP).collect();
+        render_index(&chunks)
+    } else {
+        render_urlset(&urls)
+    };
+
+    Ok(conditional_response(
+        &headers,
+        body,
+        CONTENT_TYPE,
+        last_modified(&posts),
+    ))
+}
+
+/// `/sitemaps/{n}.xml`: one chunk of a split sitemap
+pub async fn sitemap_chunk(
+    Path(file): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let number: usize = file
+        .strip_suffix(".xml")
+        .and_then(|n| n.parse().ok())
+        .filter(|n| *n > 0)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let posts = scoped_posts(&FeedScope::All).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let urls = urls(&posts);
+    let chunk = urls
+        .chunks(MAX_URLS_PER_SITEMAP)
+        .nth(number - 1)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let lastmod = chunk
+        .iter()
+        .map(|url| url.lastmod)
+        .max()
+        .unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+    Ok(conditional_response(
+        &headers,
+        render_urlset(chunk),
+        CONTENT_TYPE,
+        lastmod,
+    ))
+}
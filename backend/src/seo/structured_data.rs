@@ -0,0 +1,171 @@
+use crate::markdown::Post;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+const SCHEMA_CONTEXT: &str = "https://schema.org";
+
+/// schema.org `Person`
+#[derive(Debug, Serialize)]
+pub struct Person {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl Person {
+    pub fn new(name: &str) -> Self {
+        Self {
+            kind: "Person",
+            name: name.to_string(),
+        }
+    }
+}
+
+/// schema.org `BlogPosting` for a single post page
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlogPosting {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub headline: String,
+    pub description: String,
+    pub url: String,
+    pub main_entity_of_page: String,
+    pub author: Person,
+    pub date_published: DateTime<Utc>,
+    pub date_modified: DateTime<Utc>,
+    pub word_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<String>,
+    pub in_language: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<Image>,
+}
+
+/// An image given either by URL or described in full
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Image {
+    Url(String),
+    Object(ImageObject),
+}
+
+/// schema.org `ImageObject`, used for cover images whose size and description are known
+#[derive(Debug, Serialize)]
+pub struct ImageObject {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl BlogPosting {
+    pub fn for_post(post: &Post) -> Self {
+        let meta = crate::seo::meta::PageMeta::for_post(post);
+        let cover = post.cover.as_ref().filter(|_| post.seo.image.is_none());
+        let image = match (cover, meta.image) {
+            (Some(cover), Some(url)) => {
+                let dimensions = crate::media::image_dimensions(&cover.url);
+                Some(Image::Object(ImageObject {
+                    kind: "ImageObject",
+                    url,
+                    caption: meta.image_alt,
+                    width: dimensions.map(|(width, _)| width),
+                    height: dimensions.map(|(_, height)| height),
+                }))
+            }
+            (None, Some(url)) => Some(Image::Url(url)),
+            (_, None) => None,
+        };
+        Self {
+            context: SCHEMA_CONTEXT,
+            kind: "BlogPosting",
+            headline: post.title.clone(),
+            description: meta.description,
+            url: meta.canonical_url.clone(),
+            main_entity_of_page: meta.canonical_url,
+            author: Person::new(&post.author),
+            date_published: post.created_at,
+            date_modified: post.updated_at,
+            word_count: crate::markdown::reader::word_count(&post.content),
+            keywords: (!post.tags.is_empty()).then(|| post.tags.join(", ")),
+            in_language: post.lang.clone(),
+            image,
+        }
+    }
+}
+
+/// schema.org `ListItem` pointing at a post
+#[derive(Debug, Serialize)]
+pub struct ListItem {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub position: usize,
+    pub url: String,
+    pub name: String,
+}
+
+/// schema.org `ItemList` of posts, in display order
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemList {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub item_list_element: Vec<ListItem>,
+}
+
+/// schema.org `Blog` describing the site itself
+#[derive(Debug, Serialize)]
+pub struct Blog {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub name: String,
+    pub url: String,
+}
+
+/// Structured data for the index page: the blog and the list of its posts
+pub fn for_index(posts: &[Post]) -> (Blog, ItemList) {
+    let blog = Blog {
+        context: SCHEMA_CONTEXT,
+        kind: "Blog",
+        name: crate::utils::site_title(),
+        url: format!("{}/", crate::utils::public_base_url()),
+    };
+    let list = ItemList {
+        context: SCHEMA_CONTEXT,
+        kind: "ItemList",
+        item_list_element: posts
+            .iter()
+            .enumerate()
+            .map(|(index, post)| ListItem {
+                kind: "ListItem",
+                position: index + 1,
+                url: crate::feeds::post_url(post),
+                name: post.title.clone(),
+            })
+            .collect(),
+    };
+    (blog, list)
+}
+
+/// Serialize data into a JSON-LD script tag that cannot break out of the element
+pub fn script_tag<T: Serialize>(data: &T) -> String {
+    match serde_json::to_string(data) {
+        Ok(json) => format!(
+            r#"<script type="application/ld+json">{}</script>"#,
+            json.replace("</", "<\\/")
+        ),
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,163 @@
+use super::Label;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const MODEL_FILE: &str = "spam_model.json";
+/// Decisions of each kind needed before the model is trusted, a handful would swing it wildly
+const MIN_DOCUMENTS: u32 = 10;
+/// Tokens furthest from neutral that decide the score
+const DECIDING_TOKENS: usize = 15;
+/// Weight of the neutral prior against a token's observed frequency
+const PRIOR_STRENGTH: f64 = 1.0;
+
+/// Documents of each kind a token appeared in
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct Counts {
+    #[serde(default)]
+    spam: u32,
+    #[serde(default)]
+    ham: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Model {
+    spam_documents: u32,
+    ham_documents: u32,
+    tokens: BTreeMap<String, Counts>,
+}
+
+/// Consulted for every comment and webmention, so kept in memory once loaded
+fn cache() -> &'static Mutex<Option<Model>> {
+    static CACHE: OnceLock<Mutex<Option<Model>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(None))
+}
+
+fn read() -> Result<Model> {
+    let path = Path::new(MODEL_FILE);
+    if !path.exists() {
+        return Ok(Model::default());
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read spam_model.json")?;
+    serde_json::from_str(&content).context("Failed to parse spam_model.json")
+}
+
+fn write(model: &Model) -> Result<()> {
+    let content = serde_json::to_string(model).context("Failed to serialize spam model")?;
+    std::fs::write(MODEL_FILE, content).context("Failed to write spam_model.json")
+}
+
+/// Run `f` on the model, saving it when it reports a change
+fn with<R>(f: impl FnOnce(&mut Model) -> (R, bool)) -> Result<R> {
+    let mut cache = cache().lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.is_none() {
+        *cache = Some(read()?);
+    }
+    let model = cache.as_mut().expect("spam model is loaded");
+    let (result, changed) = f(model);
+    if changed {
+        if let Err(e) = write(model) {
+            // Reload from disk next time rather than score with training that was not saved
+            *cache = None;
+            return Err(e);
+        }
+    }
+    Ok(result)
+}
+
+/// Distinct words of a text, plus the host of every link it contains
+fn tokens(text: &str) -> BTreeSet<String> {
+    let lower = text.to_lowercase();
+    let mut tokens: BTreeSet<String> = lower
+        .split(|c: char| !(c.is_alphanumeric() || matches!(c, '\'' | '$' | '-')))
+        .map(|word| word.trim_matches(|c| c == '\'' || c == '-'))
+        .filter(|word| (2..=24).contains(&word.chars().count()))
+        .map(String::from)
+        .collect();
+
+    for (at, _) in lower.match_indices("://") {
+        let host: String = lower[at + 3..]
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '.' | '-'))
+            .collect();
+        if !host.is_empty() {
+            tokens.insert(format!("host:{}", host));
+        }
+    }
+    tokens
+}
+
+/// Spam probability of one token, pulled towards neutral while it has been seen rarely
+fn token_probability(counts: Counts, model: &Model) -> f64 {
+    let spam_frequency = counts.spam as f64 / model.spam_documents as f64;
+    let ham_frequency = counts.ham as f64 / model.ham_documents as f64;
+    let observed = spam_frequency / (spam_frequency + ham_frequency);
+    let seen = (counts.spam + counts.ham) as f64;
+    ((PRIOR_STRENGTH * 0.5 + seen * observed) / (PRIOR_STRENGTH + seen)).clamp(0.01, 0.99)
+}
+
+/// Probability that a text is spam, `None` until moderators made enough decisions of both kinds
+pub fn probability(text: &str) -> Result<Option<f64>> {
+    let tokens = tokens(text);
+    with(|model| (score(model, &tokens), false))
+}
+
+fn score(model: &Model, tokens: &BTreeSet<String>) -> Option<f64> {
+    if model.spam_documents < MIN_DOCUMENTS || model.ham_documents < MIN_DOCUMENTS {
+        return None;
+    }
+
+    let mut probabilities: Vec<f64> = tokens
+        .iter()
+        .filter_map(|token| model.tokens.get(token))
+        .map(|counts| token_probability(*counts, model))
+        .collect();
+    probabilities.sort_by(|a, b| (b - 0.5).abs().total_cmp(&(a - 0.5).abs()));
+
+    // Naive Bayes combination, in log-odds to keep long texts from underflowing
+    let log_odds: f64 = probabilities
+        .iter()
+        .take(DECIDING_TOKENS)
+        .map(|p| (p / (1.0 - p)).ln())
+        .sum();
+    Some(1.0 / (1.0 + (-log_odds).exp()))
+}
+
+/// Forget a text as `remove` and learn it as `add`, for a moderator decision that was changed
+pub fn update(text: &str, remove: Option<Label>, add: Option<Label>) -> Result<()> {
+    let tokens = tokens(text);
+    with(|model| {
+        if let Some(label) = remove {
+            match label {
+                Label::Spam => model.spam_documents = model.spam_documents.saturating_sub(1),
+                Label::Ham => model.ham_documents = model.ham_documents.saturating_sub(1),
+            }
+            for token in &tokens {
+                if let Some(counts) = model.tokens.get_mut(token) {
+                    match label {
+                        Label::Spam => counts.spam = counts.spam.saturating_sub(1),
+                        Label::Ham => counts.ham = counts.ham.saturating_sub(1),
+                    }
+                }
+            }
+            model.tokens.retain(|_, counts| counts.spam + counts.ham > 0);
+        }
+
+        if let Some(label) = add {
+            match label {
+                Label::Spam => model.spam_documents += 1,
+                Label::Ham => model.ham_documents += 1,
+            }
+            for token in tokens {
+                let counts = model.tokens.entry(token).or_default();
+                match label {
+                    Label::Spam => counts.spam += 1,
+                    Label::Ham => counts.ham += 1,
+                }
+            }
+        }
+        ((), true)
+    })
+}
@@ -0,0 +1,230 @@
+pub mod bayes;
+
+use crate::i18n::Locale;
+use crate::utils::escape_html;
+use axum::http::HeaderMap;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Name of the form field people never see and bots fill in
+pub const HONEYPOT_FIELD: &str = "website";
+/// Name of the form field holding the signed time the form was rendered
+pub const ISSUED_FIELD: &str = "issued";
+
+/// Links a human comment rarely goes beyond
+const MAX_LINKS: usize = 2;
+/// Forms sent faster than this after being rendered were not typed by a person
+const MIN_FILL_TIME: Duration = Duration::from_secs(3);
+/// Forms rendered longer ago than this are treated as if they had no token
+const MAX_FORM_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+const RATE_WINDOW: Duration = Duration::from_secs(10 * 60);
+const RATE_LIMIT: usize = 5;
+
+/// Evidence in log-odds added to the classifier's score by each heuristic
+const LINK_WEIGHT: f64 = 1.5;
+const NO_TOKEN_WEIGHT: f64 = 2.0;
+const TOO_FAST_WEIGHT: f64 = 3.0;
+const RATE_WEIGHT: f64 = 3.0;
+
+/// How a moderator classified a submission, as taught to the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Label {
+    Ham,
+    Spam,
+}
+
+/// Score of a submission between 0 and 1, with what contributed to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    pub score: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<String>,
+}
+
+impl Verdict {
+    /// Whether the submission goes to quarantine instead of the moderation queue
+    pub fn is_spam(&self) -> bool {
+        self.score >= threshold()
+    }
+}
+
+/// Score from which submissions are quarantined, from BLOG_SPAM_THRESHOLD
+fn threshold() -> f64 {
+    std::env::var("BLOG_SPAM_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse().ok())
+        .unwrap_or(0.9)
+}
+
+/// Fields of a form rendered by `form_fields`
+pub struct FormFields<'a> {
+    pub honeypot: &'a str,
+    pub issued: &'a str,
+}
+
+/// Something submitted to a public endpoint
+pub struct Submission<'a> {
+    /// Text the classifier looks at
+    pub text: &'a str,
+    /// Address of the sender as forwarded by the proxy
+    pub ip: Option<&'a str>,
+    /// Anti-bot fields, `None` for machine-sent submissions such as webmentions
+    pub form: Option<FormFields<'a>>,
+}
+
+/// Address of the client, as set by the nginx proxy in front of the backend
+pub fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+}
+
+/// Key for form tokens; tokens from before a restart count as missing
+fn signing_key() -> &'static hmac::Key {
+    static KEY: OnceLock<hmac::Key> = OnceLock::new();
+    KEY.get_or_init(|| hmac::Key::new(hmac::HMAC_SHA256, &rand::random::<[u8; 32]>()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// `<seconds>.<signature>`, so the time a form was rendered cannot be forged
+fn issue_token() -> String {
+    let issued = chrono::Utc::now().timestamp().to_string();
+    let signature = hmac::sign(signing_key(), issued.as_bytes());
+    format!("{}.{}", issued, hex(signature.as_ref()))
+}
+
+/// How long ago a token was issued, if it is one of ours and not too old
+fn token_age(token: &str) -> Option<Duration> {
+    let (issued, signature) = token.split_once('.')?;
+    let signature: Vec<u8> = (0..signature.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(signature.get(at..at + 2)?, 16).ok())
+        .collect::<Option<_>>()?;
+    hmac::verify(signing_key(), issued.as_bytes(), &signature).ok()?;
+
+    let age = chrono::Utc::now().timestamp() - issued.parse::<i64>().ok()?;
+    let age = Duration::from_secs(u64::try_from(age).ok()?);
+    (age <= MAX_FORM_AGE).then_some(age)
+}
+
+/// Hidden fields for a public form: the honeypot and the time the form was rendered
+pub fn form_fields(locale: &Locale) -> String {
+    format!(
+        r#"<div class="form-trap" aria-hidden="true"><label>{} <input type="text" name="{}" tabindex="-1" autocomplete="off"></label></div><input type="hidden" name="{}" value="{}">"#,
+        escape_html(&locale.t("spam.honeypot_label")),
+        HONEYPOT_FIELD,
+        ISSUED_FIELD,
+        issue_token()
+    )
+}
+
+/// Record a submission from an address, returning how many it sent within the window
+fn record_hit(ip: &str) -> usize {
+    static HITS: OnceLock<Mutex<HashMap<String, VecDeque<Instant>>>> = OnceLock::new();
+    let mut hits = HITS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+
+    let now = Instant::now();
+    hits.entry(ip.to_string()).or_default().push_back(now);
+    hits.retain(|_, times| {
+        while times.front().is_some_and(|time| now.duration_since(*time) > RATE_WINDOW) {
+            times.pop_front();
+        }
+        !times.is_empty()
+    });
+    hits.get(ip).map_or(0, VecDeque::len)
+}
+
+/// Whether an address sent more than `limit` requests of one kind within the rate window, this one included
+///
+/// Requests without a known address are never limited.
+pub fn rate_limited(action: &str, headers: &HeaderMap, limit: usize) -> bool {
+    client_ip(headers).is_some_and(|ip| record_hit(&format!("{} {}", action, ip)) > limit)
+}
+
+fn count_links(text: &str) -> usize {
+    let lower = text.to_lowercase();
+    lower.matches("http://").count() + lower.matches("https://").count() + lower.matches("www.").count()
+        - lower.matches("://www.").count()
+}
+
+/// Score a submission; the classifier's probability is moved by the heuristics that fire
+pub fn check(submission: &Submission) -> Verdict {
+    let mut reasons = Vec::new();
+
+    if let Some(form) = &submission.form {
+        if !form.honeypot.trim().is_empty() {
+            return Verdict {
+                score: 1.0,
+                reasons: vec!["hidden field filled in".to_string()],
+            };
+        }
+    }
+
+    let probability = match bayes::probability(submission.text) {
+        Ok(probability) => probability,
+        Err(e) => {
+            tracing::error!("Failed to run the spam classifier: {}", e);
+            None
+        }
+    };
+    let mut log_odds = match probability {
+        Some(probability) => {
+            reasons.push(format!("classifier {:.2}", probability));
+            let probability = probability.clamp(0.001, 0.999);
+            (probability / (1.0 - probability)).ln()
+        }
+        None => 0.0,
+    };
+
+    if let Some(form) = &submission.form {
+        let links = count_links(submission.text);
+        if links > MAX_LINKS {
+            log_odds += LINK_WEIGHT * (links - MAX_LINKS) as f64;
+            reasons.push(format!("{} links", links));
+        }
+
+        match token_age(form.issued) {
+            None => {
+                log_odds += NO_TOKEN_WEIGHT;
+                reasons.push("missing or expired form token".to_string());
+            }
+            Some(age) if age < MIN_FILL_TIME => {
+                log_odds += TOO_FAST_WEIGHT;
+                reasons.push(format!("sent {}s after loading the form", age.as_secs()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    if let Some(ip) = submission.ip {
+        let hits = record_hit(ip);
+        if hits > RATE_LIMIT {
+            log_odds += RATE_WEIGHT;
+            reasons.push(format!("{} submissions from this address in {} minutes", hits, RATE_WINDOW.as_secs() / 60));
+        }
+    }
+
+    Verdict {
+        score: 1.0 / (1.0 + (-log_odds).exp()),
+        reasons,
+    }
+}
+
+/// Teach the model a moderator decision, undoing what an earlier decision on the same text taught it
+pub fn learn(text: &str, previous: Option<Label>, decision: Option<Label>) {
+    if previous == decision {
+        return;
+    }
+    if let Err(e) = bayes::update(text, previous, decision) {
+        tracing::error!("Failed to train the spam classifier: {}", e);
+    }
+}
@@ -0,0 +1,153 @@
+//! Stand-ins shared by the unit tests
+
+use crate::fetcher::{FetchedPage, Fetcher};
+use crate::newsletter::mailer::{Mailer, OutgoingMail};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Held by whichever test currently owns the working directory
+static CWD: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Fresh working directory for tests that touch the JSON stores
+///
+/// The stores live in the working directory, so tests using one run one at a time. Templates
+/// are still found at `../frontend`, and the previous directory comes back on drop.
+pub struct ScratchDir {
+    root: PathBuf,
+    previous: PathBuf,
+    _guard: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl ScratchDir {
+    pub async fn new() -> Self {
+        Self::enter(CWD.lock().await)
+    }
+
+    fn enter(guard: tokio::sync::MutexGuard<'static, ()>) -> Self {
+        let root = std::env::temp_dir().join(format!("blog-test-{}", uuid::Uuid::new_v4()));
+        let backend = root.join("backend");
+        std::fs::create_dir_all(&backend).unwrap();
+        std::os::unix::fs::symlink(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../frontend"),
+            root.join("frontend"),
+        )
+        .unwrap();
+
+        let previous = std::env::current_dir().unwrap();
+        std::env::set_current_dir(&backend).unwrap();
+        Self {
+            root,
+            previous,
+            _guard: guard,
+        }
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(&self.previous);
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// A POST the code under test sent through a [`StubFetcher`]
+#[derive(Debug, Clone)]
+pub struct SentPost {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Fetcher that serves canned pages and records what is posted instead of touching the network
+#[derive(Default)]
+pub struct StubFetcher {
+    pages: Mutex<HashMap<String, FetchedPage>>,
+    posts: Mutex<Vec<SentPost>>,
+}
+
+impl StubFetcher {
+    /// Serve `body` with status 200 at `url`
+    pub fn page(self, url: &str, body: impl Into<String>) -> Self {
+        self.page_with_status(url, 200, body)
+    }
+
+    pub fn page_with_status(self, url: &str, status: u16, body: impl Into<String>) -> Self {
+        self.set_page(url, status, body);
+        self
+    }
+
+    /// Replace what is served at `url`
+    pub fn set_page(&self, url: &str, status: u16, body: impl Into<String>) {
+        self.pages.lock().unwrap().insert(
+            url.to_string(),
+            FetchedPage {
+                status,
+                link_headers: Vec::new(),
+                body: body.into(),
+            },
+        );
+    }
+
+    pub fn posts(&self) -> Vec<SentPost> {
+        self.posts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Fetcher for StubFetcher {
+    async fn get(&self, url: &str, _accept: &str) -> Result<FetchedPage> {
+        match self.pages.lock().unwrap().get(url) {
+            Some(page) => Ok(page.clone()),
+            None => bail!("No stub page at {}", url),
+        }
+    }
+
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16> {
+        self.posts.lock().unwrap().push(SentPost {
+            url: url.to_string(),
+            headers: headers.to_vec(),
+            body,
+        });
+        Ok(202)
+    }
+}
+
+/// Mailer that keeps what it is asked to send, optionally failing every time
+#[derive(Default)]
+pub struct StubMailer {
+    failing: bool,
+    sent: Mutex<Vec<(Instant, OutgoingMail)>>,
+}
+
+impl StubMailer {
+    pub fn failing() -> Self {
+        Self {
+            failing: true,
+            ..Self::default()
+        }
+    }
+
+    /// Every mail handed over, including the ones that failed
+    pub fn sent(&self) -> Vec<OutgoingMail> {
+        self.sent.lock().unwrap().iter().map(|(_, mail)| mail.clone()).collect()
+    }
+
+    pub fn sent_at(&self) -> Vec<Instant> {
+        self.sent.lock().unwrap().iter().map(|(at, _)| *at).collect()
+    }
+}
+
+#[async_trait]
+impl Mailer for StubMailer {
+    async fn send(&self, mail: &OutgoingMail) -> Result<()> {
+        self.sent.lock().unwrap().push((Instant::now(), mail.clone()));
+        if self.failing {
+            bail!("Mail to {} refused", mail.to);
+        }
+        Ok(())
+    }
+}
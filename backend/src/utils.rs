@@ -34,3 +34,54 @@ pub fn generate_unique_slug(title: &str) -> String {
 
     slug
 }
+
+/// Parse a comma-separated tag list into trimmed, lowercase, unique tags
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',').map(|t| t.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Public base URL of the blog, without a trailing slash
+pub fn public_base_url() -> String {
+    std::env::var("BLOG_PUBLIC_URL")
+        .unwrap_or_else(|_| "http://localhost".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Name of the blog shown in feeds and metadata
+pub fn site_title() -> String {
+    std::env::var("BLOG_TITLE").unwrap_or_else(|_| "Blog App".to_string())
+}
+
+/// Escape text for safe inclusion in HTML content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Random alphanumeric token, for links that must not be guessable
+pub fn random_token(len: usize) -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,141 @@
+/// The attributes of a start tag, names lowercased
+#[derive(Debug, Default)]
+pub struct Tag {
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether a space-separated `rel` contains `value`
+    pub fn has_rel(&self, value: &str) -> bool {
+        self.attribute("rel")
+            .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case(value)))
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn parse_attributes(source: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = source.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_equals) = rest.strip_prefix('=') {
+            let after_equals = after_equals.trim_start();
+            let (raw, remaining) = match after_equals.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_equals[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_equals
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after_equals.len());
+                    (&after_equals[..end], &after_equals[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining.trim_start();
+        } else if name.is_empty() {
+            // Skip a stray character such as the `/` of a self-closing tag
+            rest = rest.get(1..).unwrap_or("").trim_start();
+            continue;
+        }
+
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+    }
+    attributes
+}
+
+/// Start tags with one of the given names, in document order
+pub fn tags(html: &str, names: &[&str]) -> Vec<Tag> {
+    let mut found = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>').unwrap_or(rest.len());
+        let inside = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or("");
+
+        let name_end = inside
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(inside.len());
+        let name = inside[..name_end].to_lowercase();
+        if names.contains(&name.as_str()) {
+            found.push(Tag {
+                attributes: parse_attributes(&inside[name_end..]),
+            });
+        }
+    }
+    found
+}
+
+/// Text of the `<title>` element
+pub fn title(html: &str) -> Option<String> {
+    let lower = html.to_lowercase();
+    let start = lower.find("<title")?;
+    let content_start = start + lower[start..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find("</title")?;
+    let title = decode_entities(html[content_start..content_end].trim());
+    (!title.is_empty()).then_some(title)
+}
+
+/// Visible text of a page, without scripts, styles and markup
+pub fn text(html: &str) -> String {
+    // ASCII lowercasing keeps byte offsets valid in the original
+    let lower = html.to_ascii_lowercase();
+    let mut output = String::new();
+    let mut position = 0;
+
+    while let Some(start) = lower[position..].find('<').map(|at| position + at) {
+        output.push_str(&html[position..start]);
+        output.push(' ');
+        let end = match ["script", "style"]
+            .iter()
+            .find(|name| lower[start + 1..].starts_with(*name))
+        {
+            // Skip the element's content along with its tags
+            Some(name) => lower[start..]
+                .find(&format!("</{}", name))
+                .and_then(|close| lower[start + close..].find('>').map(|end| start + close + end)),
+            None => lower[start..].find('>').map(|end| start + end),
+        };
+        match end {
+            Some(end) => position = end + 1,
+            None => {
+                position = html.len();
+                break;
+            }
+        }
+    }
+    output.push_str(&html[position..]);
+
+    decode_entities(&output)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,341 @@
+pub mod html;
+pub mod send;
+pub mod store;
+
+use crate::fetcher::Fetcher;
+use crate::i18n::Locale;
+use crate::markdown::Post;
+use crate::spam::Label;
+use crate::utils::escape_html;
+use axum::{
+    extract::{Form, Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use store::{Mention, MentionStatus};
+
+/// Called by `markdown::writer` when a post is created or edited
+pub fn post_published(post: &Post, previous: Option<&Post>) {
+    send::notify_links(crate::fetcher::fetcher(), post, Some(post), previous);
+}
+
+/// Called by `markdown::writer` when a post is deleted, so linked pages can drop the mention
+pub fn post_deleted(previous: &Post) {
+    send::notify_links(crate::fetcher::fetcher(), previous, None, Some(previous));
+}
+
+/// Mentions are shown without review when this is set
+fn auto_approve() -> bool {
+    std::env::var("BLOG_WEBMENTION_AUTO_APPROVE").is_ok_and(|v| v == "true")
+}
+
+/// Slug of the post a target URL points at, if it is one of ours
+fn target_slug(target: &str) -> Option<String> {
+    let prefix = format!("{}/posts/", crate::utils::public_base_url());
+    let slug = target
+        .strip_prefix(&prefix)?
+        .split(['#', '?'])
+        .next()?
+        .trim_end_matches('/');
+    crate::markdown::reader::read_post(slug)
+        .ok()
+        .map(|post| post.slug)
+}
+
+/// Whether the page links to the target with an `href` or embeds it with a `src`
+fn links_to(page: &str, target: &str) -> bool {
+    html::tags(page, &["a", "link", "img", "video", "audio", "source"])
+        .iter()
+        .any(|tag| {
+            ["href", "src"]
+                .iter()
+                .filter_map(|name| tag.attribute(name))
+                .any(|url| url.trim() == target)
+        })
+}
+
+/// Characters of the source page kept for the spam filter
+const EXCERPT_CHARS: usize = 2000;
+
+/// Text the spam filter classifies and learns from
+fn spam_text(mention: &Mention) -> String {
+    format!(
+        "{}\n{}\n{}",
+        mention.source,
+        mention.title.as_deref().unwrap_or_default(),
+        mention.excerpt.as_deref().unwrap_or_default()
+    )
+}
+
+/// Check that the source still links to the target, then queue it for moderation
+///
+/// Sources the spam filter scores above its threshold are quarantined instead.
+pub async fn verify(fetcher: &dyn Fetcher, mention: Mention, ip: Option<String>) {
+    let page = match fetcher.get(&mention.source, "text/html").await {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::warn!("Could not verify webmention from {}: {}", mention.source, e);
+            return;
+        }
+    };
+
+    // A source that is gone or no longer links here withdraws the mention
+    if page.status >= 400 || !links_to(&page.body, &mention.target) {
+        if let Err(e) = store::remove(&mention.id) {
+            tracing::error!("Failed to remove webmention {}: {}", mention.id, e);
+        }
+        return;
+    }
+
+    let mut checked = mention.clone();
+    checked.title = html::title(&page.body);
+    checked.excerpt = Some(html::text(&page.body).chars().take(EXCERPT_CHARS).collect());
+    let verdict = crate::spam::check(&crate::spam::Submission {
+        text: &spam_text(&checked),
+        ip: ip.as_deref(),
+        form: None,
+    });
+
+    let mut first_verification = false;
+    let result = store::update(&mention.id, |stored| {
+        first_verification = stored.status == MentionStatus::Unverified;
+        stored.title = checked.title;
+        stored.excerpt = checked.excerpt;
+        stored.verified_at = Some(chrono::Utc::now());
+        if matches!(stored.status, MentionStatus::Unverified | MentionStatus::Pending) {
+            stored.status = if verdict.is_spam() {
+                MentionStatus::Spam
+            } else if auto_approve() {
+                MentionStatus::Approved
+            } else {
+                MentionStatus::Pending
+            };
+        }
+        stored.spam = Some(verdict);
+    });
+    match result {
+        // Authors hear about a mention once, when it is first verified, unless it went to spam
+        Ok(Some(stored)) if first_verification && stored.status != MentionStatus::Spam => {
+            if let Ok(post) = crate::markdown::reader::read_post(&stored.slug) {
+                crate::notifications::notify(
+                    crate::notifications::store::NotificationKind::MentionReceived,
+                    &post,
+                    &stored.source,
+                );
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to update webmention {}: {}", mention.id, e),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WebmentionForm {
+    source: String,
+    target: String,
+}
+
+/// `/webmention`: accept a notification and verify it in the background
+pub async fn receive(headers: HeaderMap, Form(form): Form<WebmentionForm>) -> (StatusCode, &'static str) {
+    let is_http = |url: &str| url.starts_with("http://") || url.starts_with("https://");
+    if !is_http(&form.source) || !is_http(&form.target) || form.source == form.target {
+        return (StatusCode::BAD_REQUEST, "source and target must be distinct http(s) URLs");
+    }
+    let Some(slug) = target_slug(&form.target) else {
+        return (StatusCode::BAD_REQUEST, "target is not a post on this site");
+    };
+
+    match store::receive(&form.source, &form.target, &slug) {
+        Ok(mention) => {
+            tokio::spawn(verify(
+                crate::fetcher::fetcher(),
+                mention,
+                crate::spam::client_ip(&headers),
+            ));
+            (StatusCode::ACCEPTED, "Webmention accepted for verification")
+        }
+        Err(e) => {
+            tracing::error!("Failed to store webmention: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Webmention could not be stored")
+        }
+    }
+}
+
+/// Approved mentions listed under a post
+pub fn render(slug: &str, locale: &Locale) -> String {
+    let mentions = store::mentions_for(slug, MentionStatus::Approved);
+    if mentions.is_empty() {
+        return String::new();
+    }
+
+    let items: String = mentions
+        .iter()
+        .map(|mention| {
+            format!(
+                r#"<li class="webmention"><a href="{}" rel="nofollow ugc">{}</a> <time datetime="{}">{}</time></li>"#,
+                escape_html(&mention.source),
+                escape_html(mention.title.as_deref().unwrap_or(&mention.source)),
+                mention.received_at.to_rfc3339(),
+                locale.format_date(&mention.received_at)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<section class="webmentions"><h2>{}</h2><ul>{}</ul></section>"#,
+        locale.t("webmentions.heading"),
+        items
+    )
+}
+
+#[derive(Deserialize)]
+pub struct ModerationQuery {
+    status: Option<MentionStatus>,
+}
+
+/// `/admin/webmentions`: mentions for review, optionally filtered by status
+pub async fn list(Query(query): Query<ModerationQuery>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mentions: Vec<Mention> = store::mentions()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .filter(|mention| query.status.is_none_or(|status| mention.status == status))
+        .collect();
+    Ok(Json(json!({ "success": true, "webmentions": mentions })))
+}
+
+/// Approvals and spam reports train the spam filter; a rejection only takes back earlier training
+fn moderate(id: &str, status: MentionStatus) -> Result<Json<serde_json::Value>, StatusCode> {
+    let decision = match status {
+        MentionStatus::Approved => Some(Label::Ham),
+        MentionStatus::Spam => Some(Label::Spam),
+        MentionStatus::Unverified | MentionStatus::Pending | MentionStatus::Rejected => None,
+    };
+    let mut previous = None;
+    let result = store::update(id, |mention| {
+        previous = mention.trained_as;
+        mention.status = status;
+        mention.trained_as = decision;
+    });
+    match result {
+        Ok(Some(mention)) => {
+            crate::spam::learn(&spam_text(&mention), previous, decision);
+            Ok(Json(json!({ "success": true, "webmention": mention })))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+pub async fn approve(Path(id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    moderate(&id, MentionStatus::Approved)
+}
+
+pub async fn reject(Path(id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    moderate(&id, MentionStatus::Rejected)
+}
+
+pub async fn mark_spam(Path(id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    moderate(&id, MentionStatus::Spam)
+}
+
+pub async fn delete(Path(id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    match store::remove(&id) {
+        Ok(true) => Ok(Json(json!({ "success": true }))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ScratchDir, StubFetcher};
+
+    const SOURCE: &str = "https://elsewhere.example/notes/1";
+
+    fn target() -> String {
+        format!("{}/posts/hello", crate::utils::public_base_url())
+    }
+
+    fn linking_page() -> String {
+        format!(
+            r#"<html><head><title>A reply</title></head><body><p>Nice post, <a href="{}">read it</a>.</p></body></html>"#,
+            target()
+        )
+    }
+
+    fn stored(id: &str) -> Option<Mention> {
+        store::mentions().unwrap().into_iter().find(|mention| mention.id == id)
+    }
+
+    #[tokio::test]
+    async fn queues_a_source_linking_to_the_target() {
+        let _dir = ScratchDir::new().await;
+        let source = StubFetcher::default().page(SOURCE, linking_page());
+        let mention = store::receive(SOURCE, &target(), "hello").unwrap();
+
+        verify(&source, mention.clone(), None).await;
+
+        let verified = stored(&mention.id).unwrap();
+        assert_eq!(verified.status, MentionStatus::Pending);
+        assert_eq!(verified.title.as_deref(), Some("A reply"));
+        assert!(verified.verified_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn drops_a_source_not_linking_to_the_target() {
+        let _dir = ScratchDir::new().await;
+        let source = StubFetcher::default().page(SOURCE, "<p>Nothing to see here</p>");
+        let mention = store::receive(SOURCE, &target(), "hello").unwrap();
+
+        verify(&source, mention.clone(), None).await;
+
+        assert!(stored(&mention.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn a_gone_source_withdraws_an_approved_mention() {
+        let _dir = ScratchDir::new().await;
+        let source = StubFetcher::default().page(SOURCE, linking_page());
+        let mention = store::receive(SOURCE, &target(), "hello").unwrap();
+        verify(&source, mention.clone(), None).await;
+        assert!(moderate(&mention.id, MentionStatus::Approved).is_ok());
+
+        source.set_page(SOURCE, 410, "Gone");
+        let resent = store::receive(SOURCE, &target(), "hello").unwrap();
+        verify(&source, resent, None).await;
+
+        assert!(stored(&mention.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn moderation_moves_mentions_between_statuses() {
+        let _dir = ScratchDir::new().await;
+        let source = StubFetcher::default().page(SOURCE, linking_page());
+        let mention = store::receive(SOURCE, &target(), "hello").unwrap();
+        verify(&source, mention.clone(), None).await;
+
+        assert!(moderate(&mention.id, MentionStatus::Approved).is_ok());
+        let approved = stored(&mention.id).unwrap();
+        assert_eq!(approved.status, MentionStatus::Approved);
+        assert_eq!(approved.trained_as, Some(Label::Ham));
+
+        // Verifying again keeps a moderator's decision
+        verify(&source, approved.clone(), None).await;
+        assert_eq!(stored(&mention.id).unwrap().status, MentionStatus::Approved);
+
+        assert!(moderate(&mention.id, MentionStatus::Spam).is_ok());
+        let spam = stored(&mention.id).unwrap();
+        assert_eq!(spam.status, MentionStatus::Spam);
+        assert_eq!(spam.trained_as, Some(Label::Spam));
+
+        assert!(moderate(&mention.id, MentionStatus::Rejected).is_ok());
+        let rejected = stored(&mention.id).unwrap();
+        assert_eq!(rejected.status, MentionStatus::Rejected);
+        assert_eq!(rejected.trained_as, None);
+
+        assert_eq!(moderate("missing", MentionStatus::Approved).unwrap_err(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::fetcher::Fetcher;
+use crate::markdown::Post;
+use crate::webmention::html;
+use anyhow::{bail, Result};
+use markdown::mdast::Node;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Delays before each retry of a failed notification
+const RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(10 * 60),
+    Duration::from_secs(60 * 60),
+];
+
+fn collect_links(node: &Node, links: &mut BTreeSet<String>) {
+    match node {
+        Node::Link(link) => {
+            links.insert(link.url.clone());
+        }
+        Node::Definition(definition) => {
+            links.insert(definition.url.clone());
+        }
+        _ => {}
+    }
+
+    for child in node.children().into_iter().flatten() {
+        collect_links(child, links);
+    }
+}
+
+/// External http(s) links in markdown content
+pub fn outgoing_links(content: &str) -> BTreeSet<String> {
+    let mut links = BTreeSet::new();
+    if let Ok(tree) = markdown::to_mdast(content, &markdown::ParseOptions::default()) {
+        collect_links(&tree, &mut links);
+    }
+
+    let own_site = crate::utils::public_base_url();
+    links
+        .into_iter()
+        .filter(|link| link.starts_with("http://") || link.starts_with("https://"))
+        .filter(|link| !link.starts_with(&own_site))
+        .collect()
+}
+
+/// Webmention endpoint advertised by a `Link` header value
+fn endpoint_in_link_header(value: &str) -> Option<String> {
+    value.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        let is_webmention = params.split(';').any(|param| {
+            param
+                .trim()
+                .strip_prefix("rel=")
+                .is_some_and(|rel| {
+                    rel.trim_matches('"')
+                        .split_whitespace()
+                        .any(|r| r.eq_ignore_ascii_case("webmention"))
+                })
+        });
+        is_webmention.then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+/// Find where a page accepts webmentions, resolved against the page URL
+pub async fn discover_endpoint(fetcher: &dyn Fetcher, target: &str) -> Result<Option<String>> {
+    let page = fetcher.get(target, "text/html").await?;
+    if page.status >= 400 {
+        bail!("Target {} returned {}", target, page.status);
+    }
+
+    let endpoint = page
+        .link_headers
+        .iter()
+        .find_map(|value| endpoint_in_link_header(value))
+        .or_else(|| {
+            html::tags(&page.body, &["link", "a"])
+                .into_iter()
+                .find(|tag| tag.has_rel("webmention") && tag.attribute("href").is_some())
+                .and_then(|tag| tag.attribute("href").map(String::from))
+        });
+
+    Ok(endpoint.and_then(|endpoint| {
+        reqwest::Url::parse(target)
+            .and_then(|base| base.join(&endpoint))
+            .ok()
+            .map(|url| url.to_string())
+    }))
+}
+
+/// Notify one target that `source` mentions it; targets without an endpoint are skipped
+pub async fn send(fetcher: &dyn Fetcher, source: &str, target: &str) -> Result<()> {
+    let Some(endpoint) = discover_endpoint(fetcher, target).await? else {
+        return Ok(());
+    };
+    let form = format!(
+        "source={}&target={}",
+        urlencoding::encode(source),
+        urlencoding::encode(target)
+    );
+    let headers = [(
+        "Content-Type".to_string(),
+        "application/x-www-form-urlencoded".to_string(),
+    )];
+    let status = fetcher.post(&endpoint, &headers, form.into_bytes()).await?;
+    if !(200..300).contains(&status) {
+        bail!("Endpoint {} answered {}", endpoint, status);
+    }
+    Ok(())
+}
+
+/// Notify a target in the background, retrying with backoff
+pub fn spawn_send(fetcher: &'static dyn Fetcher, source: String, target: String) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        tracing::warn!("No runtime to send webmention to {}", target);
+        return;
+    };
+
+    runtime.spawn(async move {
+        let mut delays = RETRY_DELAYS.iter();
+        loop {
+            match send(fetcher, &source, &target).await {
+                Ok(()) => return,
+                Err(e) => match delays.next() {
+                    Some(delay) => {
+                        tracing::warn!("Webmention to {} failed, retrying: {}", target, e);
+                        tokio::time::sleep(*delay).await;
+                    }
+                    None => {
+                        tracing::error!("Giving up webmention to {}: {}", target, e);
+                        return;
+                    }
+                },
+            }
+        }
+    });
+}
+
+/// Notify every page linked from the post, including links an edit or deletion removed
+pub fn notify_links(
+    fetcher: &'static dyn Fetcher,
+    source: &Post,
+    current: Option<&Post>,
+    previous: Option<&Post>,
+) {
+    let source_url = crate::feeds::post_url(source);
+    let targets: BTreeSet<String> = current
+        .into_iter()
+        .chain(previous)
+        .flat_map(|post| outgoing_links(&post.content))
+        .collect();
+
+    for target in targets {
+        spawn_send(fetcher, source_url.clone(), target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::StubFetcher;
+
+    #[tokio::test]
+    async fn sends_to_the_endpoint_the_target_advertises() {
+        let target = "https://elsewhere.example/notes/1";
+        let remote = StubFetcher::default().page(
+            target,
+            r#"<html><head><link rel="webmention" href="/webmention"></head></html>"#,
+        );
+
+        send(&remote, "https://blog.example/posts/hello", target).await.unwrap();
+
+        let posts = remote.posts();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].url, "https://elsewhere.example/webmention");
+        assert_eq!(
+            String::from_utf8(posts[0].body.clone()).unwrap(),
+            "source=https%3A%2F%2Fblog.example%2Fposts%2Fhello&target=https%3A%2F%2Felsewhere.example%2Fnotes%2F1"
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_targets_without_an_endpoint() {
+        let target = "https://elsewhere.example/plain";
+        let remote = StubFetcher::default().page(target, "<p>No webmentions here</p>");
+
+        send(&remote, "https://blog.example/posts/hello", target).await.unwrap();
+
+        assert!(remote.posts().is_empty());
+    }
+}
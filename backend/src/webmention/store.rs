@@ -0,0 +1,139 @@
+use crate::spam::{Label, Verdict};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+const WEBMENTIONS_FILE: &str = "webmentions.json";
+
+/// Where an incoming mention stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MentionStatus {
+    /// Received, the source has not been checked yet
+    Unverified,
+    /// The source links to the post, waiting for an author to review it
+    Pending,
+    Approved,
+    Rejected,
+    /// Quarantined by the spam filter or reported by a moderator
+    Spam,
+}
+
+/// An incoming webmention
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mention {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub slug: String,
+    pub status: MentionStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub received_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<DateTime<Utc>>,
+    /// Start of the source page's text, as classified by the spam filter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spam: Option<Verdict>,
+    /// Moderator decision the spam filter last learned from this mention
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trained_as: Option<Label>,
+}
+
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn read() -> Result<Vec<Mention>> {
+    let path = Path::new(WEBMENTIONS_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(path).context("Failed to read webmentions.json")?;
+    serde_json::from_str(&content).context("Failed to parse webmentions.json")
+}
+
+fn write(mentions: &[Mention]) -> Result<()> {
+    let content = serde_json::to_string_pretty(mentions).context("Failed to serialize webmentions")?;
+    std::fs::write(WEBMENTIONS_FILE, content).context("Failed to write webmentions.json")
+}
+
+pub fn mentions() -> Result<Vec<Mention>> {
+    let _guard = lock().lock().unwrap();
+    read()
+}
+
+pub fn mentions_for(slug: &str, status: MentionStatus) -> Vec<Mention> {
+    mentions()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|mention| mention.slug == slug && mention.status == status)
+        .collect()
+}
+
+/// Record a received mention, keeping the id and moderation of an earlier one from the same source
+pub fn receive(source: &str, target: &str, slug: &str) -> Result<Mention> {
+    let _guard = lock().lock().unwrap();
+    let mut mentions = read()?;
+
+    let mention = match mentions
+        .iter_mut()
+        .find(|mention| mention.source == source && mention.target == target)
+    {
+        Some(existing) => {
+            existing.received_at = Utc::now();
+            existing.clone()
+        }
+        None => {
+            let mention = Mention {
+                id: uuid::Uuid::new_v4().to_string(),
+                source: source.to_string(),
+                target: target.to_string(),
+                slug: slug.to_string(),
+                status: MentionStatus::Unverified,
+                title: None,
+                received_at: Utc::now(),
+                verified_at: None,
+                excerpt: None,
+                spam: None,
+                trained_as: None,
+            };
+            mentions.push(mention.clone());
+            mention
+        }
+    };
+
+    write(&mentions)?;
+    Ok(mention)
+}
+
+/// Apply a change to one mention, returning it if it exists
+pub fn update(id: &str, change: impl FnOnce(&mut Mention)) -> Result<Option<Mention>> {
+    let _guard = lock().lock().unwrap();
+    let mut mentions = read()?;
+    let updated = mentions.iter_mut().find(|mention| mention.id == id).map(|mention| {
+        change(mention);
+        mention.clone()
+    });
+    if updated.is_some() {
+        write(&mentions)?;
+    }
+    Ok(updated)
+}
+
+pub fn remove(id: &str) -> Result<bool> {
+    let _guard = lock().lock().unwrap();
+    let mut mentions = read()?;
+    let before = mentions.len();
+    mentions.retain(|mention| mention.id != id);
+    let removed = mentions.len() != before;
+    if removed {
+        write(&mentions)?;
+    }
+    Ok(removed)
+}
@@ -0,0 +1,49 @@
+// Live preview: editors push draft content, viewers apply streamed HTML patches
+
+// Subscribe an element to the rendered fragments of a draft
+function watchDraft(draftId, element) {
+    let lines = [];
+    const source = new EventSource(`/preview/live/${draftId}`);
+
+    function render() {
+        element.innerHTML = lines.join('');
+    }
+
+    source.addEventListener('full', function(event) {
+        const data = JSON.parse(event.data);
+        lines = data.html.split(/(?<=\n)/);
+        render();
+    });
+
+    source.addEventListener('patch', function(event) {
+        const data = JSON.parse(event.data);
+        lines.splice(data.from, data.remove, ...data.insert);
+        render();
+    });
+
+    return source;
+}
+
+// Send the editor content to the server now and whenever it changes;
+// resolves once the draft exists, so it can be watched
+function publishDraft(draftId, textarea) {
+    function send() {
+        const headers = { 'Content-Type': 'application/json' };
+        const token = localStorage.getItem('auth_token');
+        if (token) {
+            headers['Authorization'] = `Bearer ${token}`;
+        }
+
+        return fetch(`/admin/preview/${draftId}`, {
+            method: 'POST',
+            headers,
+            credentials: 'include',
+            body: JSON.stringify({ content: textarea.value })
+        }).catch(function(error) {
+            console.error('Error updating live preview:', error);
+        });
+    }
+
+    textarea.addEventListener('input', send);
+    return send();
+}
@@ -0,0 +1,53 @@
+// Media upload: store a file on the server and insert its markdown at the cursor
+
+// Upload the file picked in `input` and hand the server's answer to `onSuccess`
+function sendMedia(input, status, onSuccess) {
+    const file = input.files[0];
+    if (!file) {
+        return;
+    }
+
+    const form = new FormData();
+    form.append('file', file);
+    const headers = {};
+    const token = localStorage.getItem('auth_token');
+    if (token) {
+        headers['Authorization'] = `Bearer ${token}`;
+    }
+
+    status.textContent = '…';
+    fetch('/admin/media', { method: 'POST', headers, credentials: 'include', body: form })
+        .then(response => response.json())
+        .then(data => {
+            if (!data.success) {
+                status.textContent = data.message;
+                return;
+            }
+            onSuccess(data);
+            status.textContent = '';
+        })
+        .catch(error => {
+            console.error('Error uploading media:', error);
+            status.textContent = error.message;
+        })
+        .finally(() => {
+            input.value = '';
+        });
+}
+
+function uploadMedia(input, textarea, status) {
+    sendMedia(input, status, data => {
+        const start = textarea.selectionStart;
+        const end = textarea.selectionEnd;
+        textarea.value = textarea.value.slice(0, start) + data.markdown + textarea.value.slice(end);
+        textarea.selectionStart = textarea.selectionEnd = start + data.markdown.length;
+        textarea.dispatchEvent(new Event('input'));
+    });
+}
+
+// Use an uploaded image as the cover of the post
+function uploadCover(input, field, status) {
+    sendMedia(input, status, data => {
+        field.value = data.url;
+    });
+}